@@ -70,14 +70,16 @@ struct AnalyzerState {
     errors: Vec<CodeError>,
     in_function: bool,
     enum_variants: HashMap<String, HashSet<String>>,
+    function_names: HashSet<String>, // top level functions declared so far
 }
 
 fn new_analyzer_state() -> AnalyzerState {
-    AnalyzerState { scope_arena: ScopeArena::new(), errors: Vec::new(), in_function: false, enum_variants: HashMap::new() }
+    AnalyzerState { scope_arena: ScopeArena::new(), errors: Vec::new(), in_function: false, enum_variants: HashMap::new(), function_names: HashSet::new() }
 }
 
 pub fn checker(ast: &mut ASTNode) -> Result<(), Vec<CodeError>> {
     let mut state = new_analyzer_state();
+    check_type_annotations(ast, &mut state);
     visit_node(ast, &mut state);
     check_unused_symbols(&mut state);
 
@@ -88,10 +90,105 @@ pub fn checker(ast: &mut ASTNode) -> Result<(), Vec<CodeError>> {
     }
 }
 
+// Struct and enum names declared at the top level, which type annotations may refer to
+struct DeclaredTypes {
+    structs: HashSet<String>,
+    enums: HashSet<String>,
+}
+
+fn check_type_annotations(ast: &ASTNode, state: &mut AnalyzerState) {
+    let mut declared_types = DeclaredTypes { structs: HashSet::new(), enums: HashSet::new() };
+    if let ASTNode::Program { statements, .. } = ast {
+        for statement in statements {
+            match statement {
+                ASTNode::StructDeclaration { name, .. } => declared_types.structs.insert(name.clone()),
+                ASTNode::EnumDeclaration { name, .. } => declared_types.enums.insert(name.clone()),
+                _ => false,
+            };
+        }
+    }
+    check_annotations_in(ast, &declared_types, state);
+}
+
+fn check_annotations_in(node: &ASTNode, declared_types: &DeclaredTypes, state: &mut AnalyzerState) {
+    match node {
+        ASTNode::Program { statements, .. } | ASTNode::Block { statements, .. } => statements.iter().for_each(|statement| check_annotations_in(statement, declared_types, state)),
+        ASTNode::FunctionDeclaration { params, param_type_spans, data_type, type_span, body, .. } | ASTNode::LambdaDeclaration { params, param_type_spans, data_type, type_span, body, .. } => {
+            params.iter().zip(param_type_spans).for_each(|((_, param_type), param_type_span)| check_annotation(param_type, declared_types, state, param_type_span));
+            check_annotation(data_type, declared_types, state, type_span);
+            check_annotations_in(body, declared_types, state);
+        }
+        ASTNode::VariableDeclaration { data_type, type_span, value, .. } | ASTNode::ConstDeclaration { data_type, type_span, value, .. } => {
+            check_annotation(data_type, declared_types, state, type_span);
+            check_annotations_in(value, declared_types, state);
+        }
+        ASTNode::StructDeclaration { fields, .. } => fields.iter().for_each(|field| {
+            if let ASTNode::StructDeclarationField { data_type, type_span, .. } = field {
+                check_annotation(data_type, declared_types, state, type_span);
+            }
+        }),
+        ASTNode::IfStatement { condition_branches, else_branch, .. } => {
+            condition_branches.iter().for_each(|(_, branch)| check_annotations_in(branch, declared_types, state));
+            if let Some(branch) = else_branch {
+                check_annotations_in(branch, declared_types, state);
+            }
+        }
+        ASTNode::ReturnDeclaration { statement, .. } => check_annotations_in(statement, declared_types, state),
+        _ => {}
+    }
+}
+
+fn check_annotation(data_type: &NailDataTypeDescriptor, declared_types: &DeclaredTypes, state: &mut AnalyzerState, code_span: &CodeSpan) {
+    let (kind, name, known) = match data_type {
+        NailDataTypeDescriptor::Struct(name) | NailDataTypeDescriptor::ArrayStruct(name) => ("struct", name, &declared_types.structs),
+        NailDataTypeDescriptor::Enum(name) | NailDataTypeDescriptor::ArrayEnum(name) => ("enum", name, &declared_types.enums),
+        NailDataTypeDescriptor::Any(types) => return types.iter().for_each(|data_type| check_annotation(data_type, declared_types, state, code_span)),
+        NailDataTypeDescriptor::Fn(param_types, return_type) => {
+            param_types.iter().for_each(|data_type| check_annotation(data_type, declared_types, state, code_span));
+            return check_annotation(return_type, declared_types, state, code_span);
+        }
+        _ => return,
+    };
+
+    // `any` stands in for every struct or enum
+    if name == "any" || known.contains(name) {
+        return;
+    }
+    let message = match closest_match(name, known) {
+        Some(suggestion) => format!("Unknown {} type '{}', did you mean '{}'?", kind, name, suggestion),
+        None => format!("Unknown {} type '{}'", kind, name),
+    };
+    add_error(state, message, &mut code_span.clone());
+}
+
+// The declared name with the smallest edit distance, if it is close enough to be a likely typo
+fn closest_match<'a>(name: &str, candidates: &'a HashSet<String>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates.iter().map(|candidate| (edit_distance(name, candidate), candidate)).filter(|(distance, _)| *distance <= max_distance).min().map(|(_, candidate)| candidate.as_str())
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=right.len()).collect();
+    for (i, left_char) in left.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(left_char != *right_char);
+            row.push(substitution.min(previous_row[j + 1] + 1).min(row[j] + 1));
+        }
+        previous_row = row;
+    }
+    previous_row[right.len()]
+}
+
 fn visit_node(node: &mut ASTNode, state: &mut AnalyzerState) {
+    visit_interpolations(node, state);
     match node {
         ASTNode::Program { statements, code_span, scope } => statements.iter_mut().for_each(|statement| visit_node(statement, state)),
-        ASTNode::FunctionDeclaration { name, params, data_type, body, code_span, scope } => visit_function_declaration(name, params, data_type, body, state, code_span, scope),
+        ASTNode::FunctionDeclaration { name, name_span, params, data_type, body, code_span, scope, .. } => {
+            check_duplicate_function(name, name_span, state);
+            visit_function_declaration(name, params, data_type, body, state, code_span, scope)
+        }
         ASTNode::VariableDeclaration { name, data_type, value, code_span, .. } => visit_variable_declaration(name, data_type, value, state, code_span),
         ASTNode::ConstDeclaration { name, data_type, value, code_span, .. } => visit_const_declaration(name, data_type, value, state, code_span),
         ASTNode::BinaryOperation { left, operator, right, code_span, .. } => visit_binary_operation(left, operator, right, state, code_span),
@@ -101,17 +198,25 @@ fn visit_node(node: &mut ASTNode, state: &mut AnalyzerState) {
             }
         }
         ASTNode::IfStatement { condition_branches, else_branch, code_span, scope } => visit_if_statement(condition_branches, else_branch, state),
-        ASTNode::StructDeclaration { name, fields, code_span, scope } => visit_struct_declaration(name, fields, state, code_span),
+        ASTNode::StructDeclaration { name, fields, .. } => visit_struct_declaration(name, fields, state),
         ASTNode::EnumDeclaration { name, variants, code_span, scope } => visit_enum_declaration(name, variants, state, code_span),
         ASTNode::ArrayLiteral { elements, code_span, scope } => visit_array_literal(elements, state, code_span),
         ASTNode::FunctionCall { name, args, code_span, scope } => {
             visit_function_call(name, args, state, *scope, code_span);
         }
         ASTNode::ReturnDeclaration { statement, code_span, scope } => visit_return_declaration(statement, state, code_span),
+        ASTNode::Block { statements, .. } => statements.iter_mut().for_each(|statement| visit_node(statement, state)),
         _ => {} // Handle other cases as needed
     }
 }
 
+// Functions nested in other functions live in their own scope, so only top level ones can clash
+fn check_duplicate_function(name: &str, name_span: &CodeSpan, state: &mut AnalyzerState) {
+    if !state.in_function && !state.function_names.insert(name.to_string()) {
+        add_error(state, format!("Duplicate function '{}'", name), &mut name_span.clone());
+    }
+}
+
 fn visit_function_declaration(
     name: &str,
     params: &[(String, NailDataTypeDescriptor)],
@@ -176,7 +281,7 @@ fn visit_binary_operation(left: &ASTNode, operator: &Operation, right: &ASTNode,
     } else {
         // Determine the result type based on the operator and operand types
         match operator {
-            Operation::Add | Operation::Sub | Operation::Mul | Operation::Div => {
+            Operation::Add | Operation::Sub | Operation::Mul | Operation::Div | Operation::Mod => {
                 if left_type != NailDataTypeDescriptor::Int && left_type != NailDataTypeDescriptor::Float {
                     add_error(state, format!("Invalid operand type for arithmetic operation: {:?}", left_type), code_span);
                 }
@@ -203,11 +308,15 @@ fn visit_if_statement(condition_branches: &mut [(Box<ASTNode>, Box<ASTNode>)], e
     }
 }
 
-fn visit_struct_declaration(name: &str, fields: &[ASTNode], state: &mut AnalyzerState, code_span: &mut CodeSpan) {
+fn visit_struct_declaration(name: &str, fields: &[ASTNode], state: &mut AnalyzerState) {
+    let mut field_names = HashSet::new();
     fields.iter().for_each(|field| {
-        if let ASTNode::StructDeclarationField { name: field_name, data_type } = field {
+        if let ASTNode::StructDeclarationField { name: field_name, data_type, type_span, code_span } = field {
+            if !field_names.insert(field_name.clone()) {
+                add_error(state, format!("Duplicate field '{}' in struct '{}'", field_name, name), &mut code_span.clone());
+            }
             if matches!(data_type, NailDataTypeDescriptor::Struct(_) | NailDataTypeDescriptor::Enum(_)) {
-                add_error(state, format!("Nested structs or enums are not allowed in struct '{}', field '{}'", name, field_name), code_span);
+                add_error(state, format!("Nested structs or enums are not allowed in struct '{}', field '{}'", name, field_name), &mut type_span.clone());
             }
         }
     });
@@ -216,7 +325,7 @@ fn visit_struct_declaration(name: &str, fields: &[ASTNode], state: &mut Analyzer
 fn visit_enum_declaration(name: &str, variants: &[ASTNode], state: &mut AnalyzerState, code_span: &mut CodeSpan) {
     let mut variant_set = HashSet::new();
     variants.iter().for_each(|variant| {
-        if let ASTNode::EnumVariant { name: variant_name, code_span, .. } = variant {
+        if let ASTNode::EnumVariant { variant: variant_name, code_span, .. } = variant {
             if !variant_set.insert(variant_name.clone()) {
                 add_error(state, format!("Duplicate variant '{}' in enum '{}'", variant_name, name), &mut code_span.clone());
             }
@@ -285,10 +394,64 @@ fn visit_return_declaration(expr: &ASTNode, state: &mut AnalyzerState, code_span
     check_type(expr, state);
 }
 
+// Expressions are only type checked as a whole, so any interpolations nested inside them are found here
+fn visit_interpolations(node: &ASTNode, state: &mut AnalyzerState) {
+    match node {
+        ASTNode::StringInterpolation { parts, code_span, .. } => visit_string_interpolation(parts, state, code_span),
+        ASTNode::VariableDeclaration { value, .. } | ASTNode::ConstDeclaration { value, .. } => visit_interpolations(value, state),
+        ASTNode::ReturnDeclaration { statement, .. } => visit_interpolations(statement, state),
+        ASTNode::UnaryOperation { operand, .. } => visit_interpolations(operand, state),
+        ASTNode::BinaryOperation { left, right, .. } => {
+            visit_interpolations(left, state);
+            visit_interpolations(right, state);
+        }
+        ASTNode::FunctionCall { args: nodes, .. } | ASTNode::ArrayLiteral { elements: nodes, .. } | ASTNode::StructInstantiation { fields: nodes, .. } => {
+            nodes.iter().for_each(|node| visit_interpolations(node, state))
+        }
+        ASTNode::StructInstantiationField { value, .. } => visit_interpolations(value, state),
+        _ => {}
+    }
+}
+
+fn visit_string_interpolation(parts: &[ASTNode], state: &mut AnalyzerState, code_span: &CodeSpan) {
+    for part in parts {
+        visit_interpolations(part, state);
+        if !mark_identifiers_as_used(part, state, code_span) {
+            continue;
+        }
+        match check_type(part, state) {
+            NailDataTypeDescriptor::Int | NailDataTypeDescriptor::Float | NailDataTypeDescriptor::String | NailDataTypeDescriptor::Boolean => {}
+            NailDataTypeDescriptor::Unknown => add_error(state, "Cannot work out the type of an interpolated expression".to_string(), &mut code_span.clone()),
+            part_type => add_error(state, format!("Cannot interpolate a value of type {:?} into a string", part_type), &mut code_span.clone()),
+        }
+    }
+}
+
+// Reports every undefined identifier in an expression, returning false if there were any
+fn mark_identifiers_as_used(node: &ASTNode, state: &mut AnalyzerState, code_span: &CodeSpan) -> bool {
+    match node {
+        ASTNode::Identifier { name, .. } => {
+            let defined = mark_symbol_as_used(state, name);
+            if !defined {
+                add_error(state, format!("Undefined variable: {}", name), &mut code_span.clone());
+            }
+            defined
+        }
+        ASTNode::UnaryOperation { operand, .. } => mark_identifiers_as_used(operand, state, code_span),
+        ASTNode::BinaryOperation { left, right, .. } => {
+            let left_defined = mark_identifiers_as_used(left, state, code_span);
+            let right_defined = mark_identifiers_as_used(right, state, code_span);
+            left_defined && right_defined
+        }
+        ASTNode::FunctionCall { args, .. } => args.iter().filter(|arg| !mark_identifiers_as_used(arg, state, code_span)).count() == 0,
+        _ => true,
+    }
+}
+
 fn check_type(node: &ASTNode, state: &AnalyzerState) -> NailDataTypeDescriptor {
     match node {
         ASTNode::NumberLiteral { data_type, .. } => data_type.clone(),
-        ASTNode::StringLiteral { .. } => NailDataTypeDescriptor::String,
+        ASTNode::StringLiteral { .. } | ASTNode::StringInterpolation { .. } => NailDataTypeDescriptor::String,
         ASTNode::Identifier { name, scope, .. } => lookup_symbol(&state.scope_arena, *scope, name).map_or(NailDataTypeDescriptor::Unknown, |s| s.data_type.clone()),
         ASTNode::ReturnDeclaration { statement, .. } => check_type(statement, state),
         ASTNode::UnaryOperation { operand, .. } => check_type(operand, state),
@@ -304,18 +467,29 @@ fn check_type(node: &ASTNode, state: &AnalyzerState) -> NailDataTypeDescriptor {
         ASTNode::Program { statements, .. } => statements.last().map_or(NailDataTypeDescriptor::Unknown, |stmt| check_type(stmt, state)),
         ASTNode::FunctionDeclaration { data_type, .. } => data_type.clone(),
         ASTNode::LambdaDeclaration { data_type, .. } => data_type.clone(),
-        ASTNode::FunctionCall { name, scope, .. } => lookup_symbol(&state.scope_arena, *scope, name).map_or(NailDataTypeDescriptor::Unknown, |s| s.data_type.clone()),
+        ASTNode::FunctionCall { name, scope, .. } => match lookup_symbol(&state.scope_arena, *scope, name).map(|s| s.data_type) {
+            Some(NailDataTypeDescriptor::Fn(_, return_type)) => *return_type,
+            _ => NailDataTypeDescriptor::Unknown,
+        },
         ASTNode::VariableDeclaration { data_type, .. } => data_type.clone(),
         ASTNode::ConstDeclaration { data_type, .. } => data_type.clone(),
         ASTNode::StructDeclarationField { data_type, .. } => data_type.clone(),
         ASTNode::IfStatement { condition_branches, .. } => condition_branches.first().map_or(NailDataTypeDescriptor::Unknown, |(_, branch)| check_type(branch, state)),
         ASTNode::Block { statements, .. } => statements.last().map_or(NailDataTypeDescriptor::Unknown, |stmt| check_type(stmt, state)),
         ASTNode::StructDeclaration { name, .. } => NailDataTypeDescriptor::Struct(name.to_string()),
-        ASTNode::StructInstantiation { name, scope, .. } => lookup_symbol(&state.scope_arena, *scope, name).map_or(NailDataTypeDescriptor::Unknown, |s| s.data_type.clone()),
+        ASTNode::StructInstantiation { name, .. } => NailDataTypeDescriptor::Struct(name.to_string()),
         ASTNode::StructInstantiationField { value, .. } => check_type(value, state),
         ASTNode::EnumDeclaration { name, .. } => NailDataTypeDescriptor::Enum(name.to_string()),
         ASTNode::EnumVariant { name, .. } => NailDataTypeDescriptor::Enum(name.to_string()),
-        ASTNode::ArrayLiteral { elements, .. } => elements.first().map_or(NailDataTypeDescriptor::Unknown, |element| check_type(element, state)),
+        ASTNode::ArrayLiteral { elements, .. } => match elements.first().map(|element| check_type(element, state)) {
+            Some(NailDataTypeDescriptor::Int) => NailDataTypeDescriptor::ArrayInt,
+            Some(NailDataTypeDescriptor::Float) => NailDataTypeDescriptor::ArrayFloat,
+            Some(NailDataTypeDescriptor::String) => NailDataTypeDescriptor::ArrayString,
+            Some(NailDataTypeDescriptor::Boolean) => NailDataTypeDescriptor::ArrayBoolean,
+            Some(NailDataTypeDescriptor::Struct(name)) => NailDataTypeDescriptor::ArrayStruct(name),
+            Some(NailDataTypeDescriptor::Enum(name)) => NailDataTypeDescriptor::ArrayEnum(name),
+            _ => NailDataTypeDescriptor::Unknown,
+        },
     }
 }
 
@@ -334,7 +508,8 @@ fn add_symbol(state: &mut AnalyzerState, symbol: Symbol) {
 
 fn lookup_symbol(arena: &ScopeArena, scope: usize, name: &str) -> Option<Symbol> {
     // we don't want to modify the original scope, so we'll use a copy for traversal
-    let mut scope_for_traversal = scope;
+    // The parser leaves nodes without a scope, so those are looked up from the scope being checked
+    let mut scope_for_traversal = if scope == NO_SCOPE { arena.current_scope() } else { scope };
     loop {
         if let Some(scope_data) = arena.get_scope(scope_for_traversal) {
             if let Some(symbol) = scope_data.symbols.get(name) {
@@ -402,3 +577,85 @@ fn check_function_return(name: &str, data_type: &NailDataTypeDescriptor, body: &
         add_error(state, format!("Missing return statement in function '{}'", name), code_span);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer;
+    use crate::parser::parse;
+
+    fn check_source_errors(input: &str) -> Vec<CodeError> {
+        let mut ast = parse(lexer(input)).unwrap();
+        checker(&mut ast).err().unwrap_or_default()
+    }
+
+    fn check_source(input: &str) -> Vec<String> {
+        check_source_errors(input).into_iter().map(|error| error.message).collect()
+    }
+
+    fn span(line: usize, start_column: usize, end_column: usize) -> CodeSpan {
+        CodeSpan { start_line: line, start_column, end_line: line, end_column }
+    }
+
+    #[test]
+    fn test_duplicate_struct_fields() {
+        let errors = check_source_errors("c first:i = 1;\nstruct Point { x:i, y:i, x:f }");
+        assert_eq!(errors.iter().map(|error| (error.message.as_str(), error.code_span.clone())).collect::<Vec<_>>(), vec![("Duplicate field 'x' in struct 'Point'", span(2, 26, 27))]);
+        assert!(check_source("struct Point { x:i, y:i }").is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_enum_variants() {
+        assert_eq!(check_source("enum Color { Red, Green, Red }"), vec!["Duplicate variant 'Red' in enum 'Color'"]);
+        assert!(check_source("enum Color { Red, Green, Blue }").is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_functions() {
+        let errors = check_source_errors("fn add(yay:i, bah:i):i {\n    r yay + bah;\n}\nfn add(yay:i, bah:i):i { r yay - bah; }");
+        assert_eq!(errors.iter().map(|error| (error.message.as_str(), error.code_span.clone())).collect::<Vec<_>>(), vec![("Duplicate function 'add'", span(4, 4, 7))]);
+    }
+
+    #[test]
+    fn test_unknown_struct_type_in_annotation() {
+        assert_eq!(check_source("struct Player { health:i } fn heal(hero:struct:Playr):i { r 1; }"), vec!["Unknown struct type 'Playr', did you mean 'Player'?"]);
+        assert_eq!(
+            check_source("enum Color { Red, Green } c paint:enum:Colour = Color::Red;"),
+            vec!["Unknown enum type 'Colour', did you mean 'Color'?", "Type mismatch in const declaration named `paint`: expected Enum(\"Colour\"), got Enum(\"Color\")"]
+        );
+        assert_eq!(
+            check_source("fn make():struct:Widget { r 1; }"),
+            vec!["Unknown struct type 'Widget'", "Type mismatch in return statement of function 'make': expected Struct(\"Widget\"), got Int"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_error_points_at_the_annotation() {
+        assert_eq!(check_source_errors("enum Color { Red, Green } c paint:enum:Colour = Color::Red;")[0].code_span, span(1, 34, 46));
+        assert_eq!(check_source_errors("struct Player { health:i }\nfn heal(hero:struct:Playr):i { r 1; }")[0].code_span, span(2, 13, 26));
+        assert_eq!(check_source_errors("fn make():struct:Widget { r 1; }")[0].code_span, span(1, 10, 24));
+        assert_eq!(check_source_errors("struct Player { health:i }\nstruct Team { size:i, lead:enum:Rank }")[0].code_span, span(2, 27, 37));
+    }
+
+    #[test]
+    fn test_string_interpolation_is_checked_in_every_expression() {
+        assert_eq!(check_source("fn greet():s { r `Hi {nobody}`; }"), vec!["Undefined variable: nobody"]);
+        assert_eq!(check_source("fn shout(text:s):s { r text; } shout(`Hey {nobody}`);"), vec!["Undefined variable: nobody"]);
+        assert_eq!(check_source("fn total(count:i):s { r `Total {count + nobody}`; }"), vec!["Undefined variable: nobody"]);
+        assert!(check_source("fn greet(name:s):s { r `Hi {name}`; } greet(`friend {1}`);").is_empty());
+    }
+
+    #[test]
+    fn test_string_interpolation_inside_arrays_structs_and_calls() {
+        assert_eq!(check_source("c names:a:s = [`a {nobody}`, `b`];"), vec!["Undefined variable: nobody"]);
+        assert_eq!(check_source("struct Point { label:s } c spot:struct:Point = Point { label: `at {nobody}` };"), vec!["Undefined variable: nobody"]);
+        assert_eq!(check_source("fn upper(word:s):s { r word; } c shout:s = `{upper(nobody)}`;"), vec!["Undefined variable: nobody"]);
+        assert!(check_source("fn upper(word:s):s { r word; } c loud:s = `hey`; c shout:s = `{upper(loud)}!`;").is_empty());
+    }
+
+    #[test]
+    fn test_known_struct_type_in_annotation() {
+        let errors = check_source("struct Player { health:i } fn heal(hero:struct:Player):i { r 1; }");
+        assert!(!errors.iter().any(|error| error.starts_with("Unknown")), "{:?}", errors);
+    }
+}
@@ -47,6 +47,9 @@ pub struct ColorScheme {
     pub background: Color,
     pub comment: Color,
     pub error: Color,
+    pub bookmark: Color,
+    pub warning_background: Color,
+    pub ruler: Color,
 }
 
 /// Convert a hex color string (e.g., "#FF5733") to a `tui::style::Color`
@@ -94,6 +97,9 @@ lazy_static! {
         background: hex_to_color("#e4e5d6"),     // Very Light Gray (almost white)
         comment: hex_to_color("#7a7771"),        // Light Gray
         error: hex_to_color("#D91A60"),          // Deep Pink
+        bookmark: hex_to_color("#0550AE"),       // Dark Blue
+        warning_background: hex_to_color("#F2C4CE"), // Pale Pink
+        ruler: hex_to_color("#E5E5E6"),        // Very Light Gray
     };
 
     pub static ref DARK_THEME: ColorScheme = ColorScheme {
@@ -126,6 +132,9 @@ lazy_static! {
         background: hex_to_color("#18181a"),     // Dark background (slightly lighter than pure black)
         comment: hex_to_color("#8282a0"),        // Medium Gray
         error: hex_to_color("#E06C75"),          // Soft Red
+        bookmark: hex_to_color("#E5C07B"),       // Light Yellow
+        warning_background: hex_to_color("#5C2B30"), // Dark Red
+        ruler: hex_to_color("#2C2F36"),        // Very Dark Gray
     };
 
 
@@ -0,0 +1,310 @@
+use crate::utils::BuildStatus;
+use crate::utils::EditorMessage;
+use crate::Editor;
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+use std::cmp::Reverse;
+use std::fmt;
+use std::sync::mpsc::Sender;
+
+// Every editor action that can be run from a key or the command palette
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditorCommand {
+    ToggleTheme,
+    Build,
+    Run,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    ToggleFold,
+    NextDiagnostic,
+    PreviousDiagnostic,
+    ToggleDiagnostics,
+    ToggleBuildOutput,
+    ToggleTrailingWhitespace,
+    ToggleTranspilePreview,
+    ScrollTranspilePreviewUp,
+    ScrollTranspilePreviewDown,
+    CenterCursor,
+    TransposeChars,
+    DeleteWordBackward,
+    DeleteWordForward,
+    InsertLineBelow,
+    InsertLineAbove,
+    CommandPalette,
+}
+
+pub struct CommandInfo {
+    pub command: EditorCommand,
+    pub name: &'static str,
+    pub action: &'static str, // the name used for the command's keybinding in the .nail config, e.g. key.build=F7
+    pub default_keybinding: &'static str,
+}
+
+// The command palette lists commands in this order when nothing has been typed.
+// Most terminals send Ctrl+Enter and Shift+Enter as a plain Enter, and Ctrl+Backspace as Backspace or Ctrl+H,
+// so no default uses them.
+pub const COMMANDS: [CommandInfo; 22] = [
+    CommandInfo { command: EditorCommand::Build, name: "Build", action: "build", default_keybinding: "F7" },
+    CommandInfo { command: EditorCommand::Run, name: "Run", action: "run", default_keybinding: "F5" },
+    CommandInfo { command: EditorCommand::ToggleBuildOutput, name: "Toggle build output", action: "toggle_build_output", default_keybinding: "F10" },
+    CommandInfo { command: EditorCommand::ToggleDiagnostics, name: "Toggle diagnostics", action: "toggle_diagnostics", default_keybinding: "F9" },
+    CommandInfo { command: EditorCommand::NextDiagnostic, name: "Go to next error", action: "next_error", default_keybinding: "F8" },
+    CommandInfo { command: EditorCommand::PreviousDiagnostic, name: "Go to previous error", action: "previous_error", default_keybinding: "Shift+F8" },
+    CommandInfo { command: EditorCommand::ToggleBookmark, name: "Toggle bookmark", action: "toggle_bookmark", default_keybinding: "F2" },
+    CommandInfo { command: EditorCommand::NextBookmark, name: "Go to next bookmark", action: "next_bookmark", default_keybinding: "F3" },
+    CommandInfo { command: EditorCommand::PreviousBookmark, name: "Go to previous bookmark", action: "previous_bookmark", default_keybinding: "Shift+F3" },
+    CommandInfo { command: EditorCommand::ToggleFold, name: "Toggle fold", action: "toggle_fold", default_keybinding: "F4" },
+    CommandInfo { command: EditorCommand::ToggleTheme, name: "Toggle theme", action: "toggle_theme", default_keybinding: "F6" },
+    CommandInfo { command: EditorCommand::ToggleTrailingWhitespace, name: "Toggle trailing whitespace", action: "toggle_trailing_whitespace", default_keybinding: "Ctrl+W" },
+    CommandInfo { command: EditorCommand::ToggleTranspilePreview, name: "Toggle Rust preview", action: "toggle_transpile_preview", default_keybinding: "Ctrl+R" },
+    CommandInfo { command: EditorCommand::ScrollTranspilePreviewUp, name: "Scroll Rust preview up", action: "scroll_transpile_preview_up", default_keybinding: "Alt+Up" },
+    CommandInfo { command: EditorCommand::ScrollTranspilePreviewDown, name: "Scroll Rust preview down", action: "scroll_transpile_preview_down", default_keybinding: "Alt+Down" },
+    CommandInfo { command: EditorCommand::CenterCursor, name: "Center current line", action: "center_cursor", default_keybinding: "Ctrl+L" },
+    CommandInfo { command: EditorCommand::TransposeChars, name: "Transpose characters", action: "transpose_chars", default_keybinding: "Ctrl+T" },
+    CommandInfo { command: EditorCommand::DeleteWordBackward, name: "Delete word backward", action: "delete_word_backward", default_keybinding: "Alt+Backspace" },
+    CommandInfo { command: EditorCommand::DeleteWordForward, name: "Delete word forward", action: "delete_word_forward", default_keybinding: "Ctrl+Delete" },
+    CommandInfo { command: EditorCommand::InsertLineBelow, name: "Insert line below", action: "insert_line_below", default_keybinding: "Alt+O" },
+    CommandInfo { command: EditorCommand::InsertLineAbove, name: "Insert line above", action: "insert_line_above", default_keybinding: "Alt+Shift+O" },
+    CommandInfo { command: EditorCommand::CommandPalette, name: "Command palette", action: "command_palette", default_keybinding: "Ctrl+P" },
+];
+
+// A key plus the modifiers held with it, written like `Ctrl+Shift+P` in the config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    // Terminals report Shift+p as `P`, with or without the SHIFT modifier, so letters are kept lowercase with SHIFT set
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        let modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::SHIFT | KeyModifiers::ALT);
+        match code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => KeyChord { code: KeyCode::Char(c.to_ascii_lowercase()), modifiers: modifiers | KeyModifiers::SHIFT },
+            code => KeyChord { code, modifiers },
+        }
+    }
+
+    pub fn parse(chord: &str) -> Option<KeyChord> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "shift" => KeyModifiers::SHIFT,
+                "alt" => KeyModifiers::ALT,
+                _ => return None,
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            name if name.len() > 1 && name.starts_with('f') => KeyCode::F(name[1..].parse().ok().filter(|number| (1..=12).contains(number))?),
+            name if name.chars().count() == 1 => KeyCode::Char(name.chars().next()?),
+            _ => return None,
+        };
+
+        Some(KeyChord::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl+"), (KeyModifiers::SHIFT, "Shift+"), (KeyModifiers::ALT, "Alt+")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+pub fn default_keybindings() -> Vec<(EditorCommand, KeyChord)> {
+    COMMANDS.iter().map(|info| (info.command, KeyChord::parse(info.default_keybinding).expect("default keybindings are valid chords"))).collect()
+}
+
+// Applies `key.<action>=<chord>` lines from the config on top of the defaults.
+// Unknown actions and chords that don't parse are logged and skipped, keeping the default binding.
+pub fn keybindings_from_config(config: &str) -> Vec<(EditorCommand, KeyChord)> {
+    let mut keybindings = default_keybindings();
+    for (action, chord) in config.lines().filter_map(|line| line.strip_prefix("key.")?.split_once('=')) {
+        let Some(info) = COMMANDS.iter().find(|info| info.action == action.trim()) else {
+            log::warn!("Ignoring keybinding for unknown action '{}'", action.trim());
+            continue;
+        };
+        let Some(chord) = KeyChord::parse(chord) else {
+            log::warn!("Ignoring keybinding '{}' for '{}', keeping {}", chord.trim(), info.action, info.default_keybinding);
+            continue;
+        };
+        if let Some(binding) = keybindings.iter_mut().find(|(command, _)| *command == info.command) {
+            binding.1 = chord;
+        }
+    }
+    keybindings
+}
+
+// Only bindings that differ from the defaults are written back to the config
+pub fn keybindings_to_config(keybindings: &[(EditorCommand, KeyChord)]) -> String {
+    COMMANDS
+        .iter()
+        .zip(default_keybindings())
+        .filter_map(|(info, (_, default_chord))| {
+            let (_, chord) = keybindings.iter().find(|(command, _)| *command == info.command)?;
+            (*chord != default_chord).then(|| format!("key.{}={}\n", info.action, chord))
+        })
+        .collect()
+}
+
+pub fn execute_command(editor: &mut Editor, command: EditorCommand, tx_build: &Sender<EditorMessage>) {
+    match command {
+        EditorCommand::ToggleTheme => editor.toggle_theme(),
+        EditorCommand::Build | EditorCommand::Run => {
+            if editor.build_status == BuildStatus::Idle {
+                let _ = tx_build.send(if command == EditorCommand::Build { EditorMessage::BuildStart } else { EditorMessage::RunStart });
+            }
+        }
+        EditorCommand::ToggleBookmark => editor.toggle_bookmark(),
+        EditorCommand::NextBookmark => editor.next_bookmark(),
+        EditorCommand::PreviousBookmark => editor.previous_bookmark(),
+        EditorCommand::ToggleFold => editor.toggle_fold(),
+        EditorCommand::NextDiagnostic => editor.goto_next_diagnostic(),
+        EditorCommand::PreviousDiagnostic => editor.goto_previous_diagnostic(),
+        EditorCommand::ToggleDiagnostics => editor.toggle_diagnostics(),
+        EditorCommand::ToggleBuildOutput => editor.toggle_build_output(),
+        EditorCommand::ToggleTrailingWhitespace => editor.toggle_trailing_whitespace(),
+        EditorCommand::ToggleTranspilePreview => editor.toggle_transpile_preview(),
+        EditorCommand::ScrollTranspilePreviewUp => editor.scroll_transpile_preview_up(),
+        EditorCommand::ScrollTranspilePreviewDown => editor.scroll_transpile_preview_down(),
+        EditorCommand::CenterCursor => editor.center_cursor_in_viewport(),
+        EditorCommand::TransposeChars => editor.transpose_chars(),
+        EditorCommand::DeleteWordBackward => editor.delete_word_backward(),
+        EditorCommand::DeleteWordForward => editor.delete_word_forward(),
+        EditorCommand::InsertLineBelow => editor.insert_line_below(),
+        EditorCommand::InsertLineAbove => editor.insert_line_above(),
+        EditorCommand::CommandPalette => editor.open_command_palette(),
+    }
+}
+
+// Commands whose name fuzzy matches the query, best match first
+pub fn matching_commands(query: &str) -> Vec<&'static CommandInfo> {
+    let mut matches: Vec<(i32, &'static CommandInfo)> = COMMANDS.iter().filter_map(|info| fuzzy_score(query, info.name).map(|score| (score, info))).collect();
+    matches.sort_by_key(|(score, _)| Reverse(*score)); // stable, so equal scores keep the table order
+    matches.into_iter().map(|(_, info)| info).collect()
+}
+
+// Scores `candidate` if every character of `query` appears in it in order, ignoring case.
+// Matches that run on from the previous one or start a word score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut search_from = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars() {
+        let index = (search_from..candidate.len()).find(|&index| candidate[index] == query_char)?;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 2;
+        }
+        if index == 0 || candidate[index - 1] == ' ' {
+            score += 3;
+        }
+        previous_match = Some(index);
+        search_from = index + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_requires_an_in_order_subsequence() {
+        assert!(fuzzy_score("tgth", "Toggle theme").is_some());
+        assert!(fuzzy_score("TOGGLE", "Toggle theme").is_some());
+        assert_eq!(fuzzy_score("ht", "Toggle theme"), None);
+        assert_eq!(fuzzy_score("", "Build"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts_and_runs() {
+        assert!(fuzzy_score("tt", "Toggle theme") > fuzzy_score("gg", "Toggle theme"));
+        assert!(fuzzy_score("og", "Toggle theme") > fuzzy_score("oe", "Toggle theme"));
+    }
+
+    #[test]
+    fn test_matching_commands() {
+        let names: Vec<&str> = matching_commands("run").iter().map(|info| info.name).collect();
+        assert_eq!(names.first(), Some(&"Run"));
+
+        let names: Vec<&str> = matching_commands("tb").iter().map(|info| info.name).collect();
+        assert!(names.contains(&"Toggle bookmark"));
+        assert!(names.contains(&"Toggle build output"));
+        assert!(!names.contains(&"Run"));
+
+        assert_eq!(matching_commands("").len(), COMMANDS.len());
+    }
+
+    #[test]
+    fn test_key_chord_parse() {
+        assert_eq!(KeyChord::parse("F7"), Some(KeyChord { code: KeyCode::F(7), modifiers: KeyModifiers::NONE }));
+        assert_eq!(KeyChord::parse("ctrl+shift+p"), Some(KeyChord { code: KeyCode::Char('p'), modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT }));
+        assert_eq!(KeyChord::parse("Ctrl+P"), KeyChord::parse("Ctrl+p"));
+        assert_eq!(KeyChord::parse("Shift+F3").map(|chord| chord.to_string()), Some("Shift+F3".to_string()));
+        assert_eq!(KeyChord::parse("Ctrl+Shift+P").map(|chord| chord.to_string()), Some("Ctrl+Shift+P".to_string()));
+        assert_eq!(KeyChord::parse("Hyper+P"), None);
+        assert_eq!(KeyChord::parse("F13"), None);
+        assert_eq!(KeyChord::parse(""), None);
+    }
+
+    #[test]
+    fn test_default_keybindings_are_distinct() {
+        let keybindings = default_keybindings();
+        for (index, (command, chord)) in keybindings.iter().enumerate() {
+            assert!(!keybindings[index + 1..].iter().any(|(_, other)| other == chord), "{:?} shares {} with another command", command, chord);
+        }
+    }
+
+    #[test]
+    fn test_key_chord_matches_uppercase_key_events() {
+        let chord = KeyChord::parse("Ctrl+Shift+P").unwrap();
+        assert_eq!(KeyChord::new(KeyCode::Char('P'), KeyModifiers::CONTROL), chord);
+        assert_eq!(KeyChord::new(KeyCode::Char('P'), KeyModifiers::CONTROL | KeyModifiers::SHIFT), chord);
+        assert_ne!(KeyChord::new(KeyCode::Char('p'), KeyModifiers::CONTROL), chord);
+    }
+
+    #[test]
+    fn test_keybindings_from_config() {
+        let keybindings = keybindings_from_config("theme=dark\nkey.build=Ctrl+B\nkey.run=Ctrl+Nope\nkey.fly=F1\n");
+        let binding = |command| keybindings.iter().find(|(bound, _)| *bound == command).map(|(_, chord)| chord.to_string());
+        assert_eq!(binding(EditorCommand::Build), Some("Ctrl+B".to_string()));
+        assert_eq!(binding(EditorCommand::Run), Some("F5".to_string()));
+        assert_eq!(keybindings.len(), COMMANDS.len());
+
+        assert_eq!(keybindings_to_config(&keybindings), "key.build=Ctrl+B\n");
+        assert_eq!(keybindings_to_config(&default_keybindings()), "");
+    }
+}
@@ -39,6 +39,8 @@ pub struct StructInstantiationDataField {
 pub struct StructDeclarationDataField {
     pub name: String,
     pub data_type: NailDataTypeDescriptor,
+    pub type_span: CodeSpan,
+    pub code_span: CodeSpan, // the field name
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -136,6 +138,8 @@ pub enum TokenType {
     Operator(Operation),                     // For operators like +, -, *, /
     Comma,                                   // For commas
     StringLiteral(String),                   // For string literals
+    InterpolatedString(Vec<Token>),          // For string literals with {expression} pieces, alternating text and interpolations
+    Interpolation(Vec<Token>),               // For an {expression} inside an interpolated string
     TypeDeclaration(NailDataTypeDescriptor), // For explicit type declarations
     ParenthesisOpen,                         // For parenthesis open
     ParenthesisClose,                        // For parenthesis close
@@ -156,6 +160,7 @@ pub enum Operation {
     Sub, // "-"
     Mul, // "*"
     Div, // "/"
+    Mod, // "%"
     Eq,  // "=="
     Ne,  // "!="
     Lt,  // "<"
@@ -170,6 +175,8 @@ pub enum Operation {
 }
 
 impl Operation {
+    // Binding strength used by precedence climbing in parse_expression, higher binds tighter, so
+    // `a + b % c == d` parses as `(a + (b % c)) == d`. Operators on the same level associate to the left.
     pub fn precedence(&self) -> u8 {
         match self {
             Operation::Or => 0,
@@ -177,7 +184,7 @@ impl Operation {
             Operation::Eq | Operation::Ne => 2,
             Operation::Lt | Operation::Lte | Operation::Gt | Operation::Gte => 3,
             Operation::Add | Operation::Sub => 4,
-            Operation::Mul | Operation::Div => 5,
+            Operation::Mul | Operation::Div | Operation::Mod => 5,
             Operation::Not | Operation::Neg => 6, // Highest precedence for unary operators
         }
     }
@@ -194,6 +201,7 @@ impl Display for Operation {
             Operation::Sub => write!(f, "-"),
             Operation::Mul => write!(f, "*"),
             Operation::Div => write!(f, "/"),
+            Operation::Mod => write!(f, "%"),
             Operation::Eq => write!(f, "=="),
             Operation::Ne => write!(f, "!="),
             Operation::Lt => write!(f, "<"),
@@ -923,7 +931,7 @@ fn is_single_character_token(chars: &mut std::iter::Peekable<std::str::Chars>) -
 
     match lookahead.next() {
         Some(c) => match c {
-            '(' | ')' | ';' | '{' | '}' | ',' | '!' | '+' | '-' | '*' | '/' | '=' | '<' | '>' => {
+            '(' | ')' | ';' | '{' | '}' | ',' | '!' | '+' | '-' | '*' | '/' | '%' | '=' | '<' | '>' => {
                 // Check if it's followed by a space, or by something it's allowed to be beside or end of input
                 match lookahead.next() {
                     Some(next_char) => {
@@ -972,6 +980,7 @@ fn lex_single_character_token(chars: &mut std::iter::Peekable<std::str::Chars>,
         '-' => TokenType::Operator(Operation::Sub),
         '*' => TokenType::Operator(Operation::Mul),
         '/' => TokenType::Operator(Operation::Div),
+        '%' => TokenType::Operator(Operation::Mod),
         '<' => TokenType::Operator(Operation::Lt),
         '>' => TokenType::Operator(Operation::Gt),
         _ => panic!("Unrecognized operator: {}", c),
@@ -1051,6 +1060,7 @@ fn lex_struct_declaration(chars: &mut std::iter::Peekable<std::str::Chars>, stat
         }
 
         // Parse field name
+        let (name_start_line, name_start_column) = (state.line, state.column);
         let mut field_name = String::new();
         while let Some(&c) = chars.peek() {
             if is_in_alphabet_or_number(c) || c == '_' {
@@ -1061,11 +1071,15 @@ fn lex_struct_declaration(chars: &mut std::iter::Peekable<std::str::Chars>, stat
             }
         }
 
+        let name_span = CodeSpan { start_line: name_start_line, start_column: name_start_column, end_line: state.line, end_column: state.column };
+
         // Parse field type
         let field_type = lex_type_system_type(chars, state);
 
         fields.push(StructDeclarationDataField {
             name: field_name,
+            type_span: CodeSpan { start_line: field_type.start_line, start_column: field_type.start_column, end_line: field_type.end_line, end_column: field_type.end_column },
+            code_span: name_span,
             data_type: match field_type {
                 LexerOutput { token_type: TokenType::TypeDeclaration(t), .. } => t,
                 _ => {
@@ -1384,16 +1398,76 @@ fn lex_number(chars: &mut std::iter::Peekable<std::str::Chars>, state: &mut Lexe
     LexerOutput { token_type, start_line, start_column, end_line: state.line, end_column: state.column }
 }
 
+// Strings may embed expressions as `Hello, {name}!`, write {{ and }} for literal braces
 fn lex_string_literal(chars: &mut std::iter::Peekable<std::str::Chars>, state: &mut LexerState) -> LexerOutput {
     let start_line = state.line;
     let start_column = state.column;
     advance(chars, state); // Skip opening quote
     let mut string_literal = String::new();
+    let mut parts: Vec<Token> = Vec::new();
+    let mut text_start = (state.line, state.column);
+    let mut error: Option<String> = None;
     while let Some(c) = advance(chars, state) {
-        if c == '`' {
-            return LexerOutput { token_type: TokenType::StringLiteral(string_literal), start_line, start_column, end_line: state.line, end_column: state.column };
+        match c {
+            '`' => {
+                let token_type = if let Some(message) = error {
+                    TokenType::LexerError(message)
+                } else if parts.is_empty() {
+                    TokenType::StringLiteral(string_literal)
+                } else {
+                    if !string_literal.is_empty() {
+                        let code_span = CodeSpan { start_line: text_start.0, start_column: text_start.1, end_line: state.line, end_column: state.column - 1 };
+                        parts.push(Token { token_type: TokenType::StringLiteral(string_literal), code_span });
+                    }
+                    TokenType::InterpolatedString(parts)
+                };
+                return LexerOutput { token_type, start_line, start_column, end_line: state.line, end_column: state.column };
+            }
+            '{' | '}' if chars.peek() == Some(&c) => {
+                advance(chars, state);
+                string_literal.push(c);
+            }
+            '{' => {
+                if !string_literal.is_empty() {
+                    let code_span = CodeSpan { start_line: text_start.0, start_column: text_start.1, end_line: state.line, end_column: state.column - 1 };
+                    parts.push(Token { token_type: TokenType::StringLiteral(std::mem::take(&mut string_literal)), code_span });
+                }
+
+                let mut expression_state = LexerState { line: state.line, column: state.column };
+                let mut expression = String::new();
+                let mut depth = 1;
+                while let Some(&next) = chars.peek() {
+                    if next == '`' {
+                        break;
+                    }
+                    advance(chars, state);
+                    match next {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    expression.push(next);
+                }
+
+                if depth > 0 {
+                    error.get_or_insert_with(|| "Unterminated interpolation in string literal, expected }".to_string());
+                } else if expression.trim().is_empty() {
+                    error.get_or_insert_with(|| "Empty interpolation in string literal".to_string());
+                } else {
+                    let code_span = CodeSpan { start_line: expression_state.line, start_column: expression_state.column, end_line: state.line, end_column: state.column - 1 };
+                    let expression_tokens = lexer_inner(&expression, &mut expression_state);
+                    parts.push(Token { token_type: TokenType::Interpolation(expression_tokens), code_span });
+                }
+                text_start = (state.line, state.column);
+            }
+            '}' => {
+                error.get_or_insert_with(|| "Unmatched } in string literal, write }} for a literal brace".to_string());
+            }
+            _ => string_literal.push(c),
         }
-        string_literal.push(c);
     }
 
     LexerOutput { token_type: TokenType::LexerError("Unterminated string literal".to_string()), start_line, start_column, end_line: state.line, end_column: state.column }
@@ -1938,8 +2012,18 @@ v points:a:struct:Point = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
                     token_type: StructDeclaration(StructDeclarationData {
                         name: "Point".to_string(),
                         fields: vec![
-                            StructDeclarationDataField { name: "x".to_string(), data_type: NailDataTypeDescriptor::Int },
-                            StructDeclarationDataField { name: "y".to_string(), data_type: NailDataTypeDescriptor::Int }
+                            StructDeclarationDataField {
+                                name: "x".to_string(),
+                                data_type: NailDataTypeDescriptor::Int,
+                                type_span: CodeSpan { start_line: 2, end_line: 2, start_column: 17, end_column: 19 },
+                                code_span: CodeSpan { start_line: 2, end_line: 2, start_column: 16, end_column: 17 }
+                            },
+                            StructDeclarationDataField {
+                                name: "y".to_string(),
+                                data_type: NailDataTypeDescriptor::Int,
+                                type_span: CodeSpan { start_line: 2, end_line: 2, start_column: 22, end_column: 24 },
+                                code_span: CodeSpan { start_line: 2, end_line: 2, start_column: 21, end_column: 22 }
+                            }
                         ]
                     }),
                     code_span: CodeSpan { start_line: 2, end_line: 2, start_column: 1, end_column: 26 }
@@ -2000,8 +2084,18 @@ v points:a:struct:Point = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
                 token_type: StructDeclaration(StructDeclarationData {
                     name: "Point".to_string(),
                     fields: vec![
-                        StructDeclarationDataField { name: "x".to_string(), data_type: NailDataTypeDescriptor::Int },
-                        StructDeclarationDataField { name: "y".to_string(), data_type: NailDataTypeDescriptor::Int }
+                        StructDeclarationDataField {
+                            name: "x".to_string(),
+                            data_type: NailDataTypeDescriptor::Int,
+                            type_span: CodeSpan { start_line: 1, end_line: 1, start_column: 17, end_column: 19 },
+                            code_span: CodeSpan { start_line: 1, end_line: 1, start_column: 16, end_column: 17 }
+                        },
+                        StructDeclarationDataField {
+                            name: "y".to_string(),
+                            data_type: NailDataTypeDescriptor::Int,
+                            type_span: CodeSpan { start_line: 1, end_line: 1, start_column: 22, end_column: 24 },
+                            code_span: CodeSpan { start_line: 1, end_line: 1, start_column: 21, end_column: 22 }
+                        }
                     ]
                 }),
                 code_span: CodeSpan { start_line: 1, end_line: 1, start_column: 1, end_column: 26 }
@@ -2222,4 +2316,34 @@ v points:a:struct:Point = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
             panic!("Expected RustEscape token");
         }
     }
+
+    #[test]
+    fn test_string_interpolation() {
+        let result = lexer("`Hello, {name}! You are {age + 1}. {{braces}}`");
+        assert_eq!(result.len(), 1);
+        if let TokenType::InterpolatedString(parts) = &result[0].token_type {
+            assert_eq!(parts.len(), 5);
+            assert!(matches!(&parts[0].token_type, TokenType::StringLiteral(s) if s == "Hello, "));
+            if let TokenType::Interpolation(expression_tokens) = &parts[1].token_type {
+                assert_eq!(expression_tokens.len(), 1);
+                assert!(matches!(&expression_tokens[0].token_type, TokenType::Identifier(s) if s == "name"));
+                assert_eq!(expression_tokens[0].code_span, CodeSpan { start_line: 1, start_column: 10, end_line: 1, end_column: 14 });
+            } else {
+                panic!("Expected Interpolation");
+            }
+            assert!(matches!(&parts[2].token_type, TokenType::StringLiteral(s) if s == "! You are "));
+            assert!(matches!(&parts[3].token_type, TokenType::Interpolation(expression_tokens) if expression_tokens.len() == 3));
+            assert!(matches!(&parts[4].token_type, TokenType::StringLiteral(s) if s == ". {braces}"));
+        } else {
+            panic!("Expected InterpolatedString token");
+        }
+    }
+
+    #[test]
+    fn test_string_interpolation_errors() {
+        assert!(matches!(&lexer("`{}`")[0].token_type, TokenType::LexerError(_)));
+        assert!(matches!(&lexer("`{name`")[0].token_type, TokenType::LexerError(_)));
+        assert!(matches!(&lexer("`name}`")[0].token_type, TokenType::LexerError(_)));
+        assert!(matches!(&lexer("`{{}}`")[0].token_type, TokenType::StringLiteral(s) if s == "{}"));
+    }
 }
@@ -1,12 +1,15 @@
 mod checker;
 mod colorizer;
+mod commands;
 mod lexer;
 mod parser;
 mod statics_for_tests;
 mod transpilier;
 use crate::colorizer::ColorScheme;
+use crate::commands::{default_keybindings, keybindings_from_config, keybindings_to_config, matching_commands, EditorCommand, KeyChord};
 use crate::utils::create_welcome_message;
 use crate::utils::lex_and_parse_thread_logic;
+use crate::utils::lexing_errors;
 use std::backtrace::Backtrace;
 use std::panic;
 mod utils;
@@ -29,19 +32,21 @@ use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
+use std::time::{Duration, Instant};
 
 use utils::lock;
 use utils::BuildStatus;
+use utils::DialogMode;
 
 use crate::lexer::CodeSpan;
 use ratatui::crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -78,11 +83,38 @@ struct Editor {
     cursor_x: usize,
     cursor_y: usize,
     build_status: BuildStatus,
-    code_error: Option<CodeError>,
+    build_started: Option<Instant>,
+    build_time: Option<Duration>, // how long the last successful build took
+    code_errors: Vec<CodeError>,
+    compile_errors: Vec<CodeError>, // from the last failed cargo build, kept until the next build starts
     tokens: Vec<lexer::Token>,
     scroll_state: ScrollbarState,
     scroll_position: u16,
+    horizontal_scroll: u16,
+    recenter_count: usize, // presses of center current line in a row, cycling center, top and bottom
     tab_index: usize,
+    bookmarks: Vec<usize>,
+    folds: Vec<(usize, usize)>, // (first line, last line) of each folded block, sorted by first line
+    viewport_height: usize,
+    viewport_width: usize,
+    dialog_mode: DialogMode,
+    diagnostics_index: usize,
+    current_diagnostic: Option<usize>,
+    status_message: Option<(String, Instant)>,
+    show_trailing_whitespace: bool,
+    rulers: Vec<usize>,             // columns to draw a vertical guide at, e.g. 80 and 100
+    max_line_length: Option<usize>, // characters past this column are flagged
+    auto_indent: bool,
+    debounce: Duration,        // how long typing has to pause before the source is re-analysed
+    build_output: Vec<String>, // raw stderr and stdout of the last cargo run
+    build_output_scroll: u16,
+    command_palette_query: String,
+    command_palette_index: usize,
+    keybindings: Vec<(EditorCommand, KeyChord)>,
+    show_transpile_preview: bool,
+    transpile_preview: Vec<String>, // Rust from the last code that transpiled
+    transpile_preview_stale: bool,  // the current code no longer transpiles
+    transpile_preview_scroll: u16,
 }
 
 impl Editor {
@@ -93,20 +125,51 @@ impl Editor {
             cursor_x: 0,
             cursor_y: 0,
             build_status: BuildStatus::Idle,
-            code_error: None,
+            build_started: None,
+            build_time: None,
+            code_errors: Vec::new(),
+            compile_errors: Vec::new(),
             tokens: Vec::new(),
             scroll_state: ScrollbarState::default(),
             scroll_position: 0,
+            horizontal_scroll: 0,
+            recenter_count: 0,
             tab_index: 0,
+            bookmarks: Vec::new(),
+            folds: Vec::new(),
+            viewport_height: 0,
+            viewport_width: 0,
+            dialog_mode: DialogMode::None,
+            diagnostics_index: 0,
+            current_diagnostic: None,
+            status_message: None,
+            show_trailing_whitespace: false,
+            rulers: Vec::new(),
+            max_line_length: None,
+            auto_indent: true,
+            debounce: Duration::from_millis(150),
+            build_output: Vec::new(),
+            build_output_scroll: 0,
+            command_palette_query: String::new(),
+            command_palette_index: 0,
+            keybindings: default_keybindings(),
+            show_transpile_preview: false,
+            transpile_preview: Vec::new(),
+            transpile_preview_stale: false,
+            transpile_preview_scroll: 0,
         }
     }
 
     fn delete_char(&mut self) {
         if self.cursor_x > 0 {
+            self.unfold_lines(self.cursor_y, self.cursor_y);
             self.content[self.cursor_y].remove(self.cursor_x - 1);
             self.cursor_x -= 1;
         } else if self.cursor_y > 0 {
+            self.unfold_lines(self.cursor_y - 1, self.cursor_y);
             let current_line = self.content.remove(self.cursor_y);
+            self.shift_bookmarks_after_join(self.cursor_y);
+            self.shift_folds_after_removal(self.cursor_y);
             self.cursor_y -= 1;
             self.cursor_x = self.content[self.cursor_y].len();
             self.content[self.cursor_y].push_str(&current_line);
@@ -118,20 +181,78 @@ impl Editor {
             self.content.push(String::new());
         }
 
+        self.unfold_lines(self.cursor_y, self.cursor_y);
         let line = &mut self.content[self.cursor_y];
         if self.cursor_x > line.len() {
             line.push_str(&" ".repeat(self.cursor_x - line.len()));
         }
 
+        // A closing brace typed as the first thing on a line ends the block, so it steps back a level
+        let before_cursor = &line[..self.cursor_x];
+        if c == '}' && self.auto_indent && before_cursor.trim().is_empty() && before_cursor.ends_with(INDENT) {
+            line.replace_range(self.cursor_x - INDENT.len()..self.cursor_x, "");
+            self.cursor_x -= INDENT.len();
+        }
+
         line.insert(self.cursor_x, c);
         self.cursor_x += 1;
     }
 
+    // At the start of a line this joins it onto the previous one, like Backspace
+    fn delete_word_backward(&mut self) {
+        let cursor_x = self.cursor_x.min(self.content[self.cursor_y].len());
+        if cursor_x == 0 {
+            self.cursor_x = 0;
+            self.delete_char();
+            return;
+        }
+
+        let start = find_prev_word_boundary(&self.content[self.cursor_y], cursor_x);
+        self.unfold_lines(self.cursor_y, self.cursor_y);
+        self.content[self.cursor_y].replace_range(start..cursor_x, "");
+        self.cursor_x = start;
+    }
+
+    // At the end of a line this joins the next line onto it
+    fn delete_word_forward(&mut self) {
+        let line_len = self.content[self.cursor_y].len();
+        if self.cursor_x < line_len {
+            let end = find_next_word_boundary(&self.content[self.cursor_y], self.cursor_x);
+            self.unfold_lines(self.cursor_y, self.cursor_y);
+            self.content[self.cursor_y].replace_range(self.cursor_x..end, "");
+        } else if self.cursor_y + 1 < self.content.len() {
+            self.unfold_lines(self.cursor_y, self.cursor_y + 1);
+            let next_line = self.content.remove(self.cursor_y + 1);
+            self.shift_bookmarks_after_join(self.cursor_y + 1);
+            self.shift_folds_after_removal(self.cursor_y + 1);
+            self.content[self.cursor_y].push_str(&" ".repeat(self.cursor_x - line_len));
+            self.content[self.cursor_y].push_str(&next_line);
+        }
+    }
+
+    // Swaps the characters either side of the cursor and moves past them, at the end of a line the last two are swapped
+    fn transpose_chars(&mut self) {
+        let line = &self.content[self.cursor_y];
+        if self.cursor_x == 0 || line.chars().count() < 2 {
+            return;
+        }
+
+        let swap_at = if self.cursor_x >= line.len() { line.len() - line.chars().next_back().map_or(0, char::len_utf8) } else { self.cursor_x };
+        let (Some(before), Some(after)) = (line[..swap_at].chars().next_back(), line[swap_at..].chars().next()) else {
+            return;
+        };
+        let (start, end) = (swap_at - before.len_utf8(), swap_at + after.len_utf8());
+
+        self.unfold_lines(self.cursor_y, self.cursor_y);
+        self.content[self.cursor_y].replace_range(start..end, &format!("{}{}", after, before));
+        self.cursor_x = end;
+    }
+
     fn move_cursor_left(&mut self) {
         if self.cursor_x > 0 {
             self.cursor_x -= 1;
-        } else if self.cursor_y > 0 {
-            self.cursor_y -= 1;
+        } else if let Some(line) = self.previous_visible_line(self.cursor_y) {
+            self.cursor_y = line;
             self.cursor_x = self.content[self.cursor_y].len();
         }
     }
@@ -140,33 +261,62 @@ impl Editor {
         let current_line_len = self.content[self.cursor_y].len();
         if self.cursor_x < current_line_len {
             self.cursor_x += 1;
-        } else if self.cursor_y < self.content.len() - 1 {
-            self.cursor_y += 1;
+        } else if let Some(line) = self.next_visible_line(self.cursor_y) {
+            self.cursor_y = line;
             self.cursor_x = 0;
         }
     }
 
     fn move_cursor_up(&mut self) {
-        if self.cursor_y > 0 {
-            self.cursor_y -= 1;
+        if let Some(line) = self.previous_visible_line(self.cursor_y) {
+            self.cursor_y = line;
             let upper_line_len = self.content[self.cursor_y].len();
             self.cursor_x = self.cursor_x.min(upper_line_len);
         }
     }
 
     fn move_cursor_down(&mut self) {
-        if self.cursor_y < self.content.len() - 1 {
-            self.cursor_y += 1;
+        if let Some(line) = self.next_visible_line(self.cursor_y) {
+            self.cursor_y = line;
             let lower_line_len = self.content[self.cursor_y].len();
             self.cursor_x = self.cursor_x.min(lower_line_len);
         }
     }
 
     fn insert_newline(&mut self) {
+        self.unfold_lines(self.cursor_y, self.cursor_y);
+        // Splitting at the start of a line pushes the whole line down, bookmark and fold included
+        let first_moved_line = if self.cursor_x == 0 { self.cursor_y } else { self.cursor_y + 1 };
+        self.shift_bookmarks_after_insert(first_moved_line);
+        self.shift_folds_after_insert(first_moved_line);
         let remaining = self.content[self.cursor_y].split_off(self.cursor_x);
+        let indent = if self.auto_indent { calculate_auto_indent(&self.content[self.cursor_y]) } else { String::new() };
         self.cursor_y += 1;
-        self.content.insert(self.cursor_y, remaining);
-        self.cursor_x = 0;
+        self.cursor_x = indent.len();
+        let remaining = if self.auto_indent { remaining.trim_start() } else { &remaining };
+        self.content.insert(self.cursor_y, indent + remaining);
+    }
+
+    // Opens an empty line under the current one without splitting it, indented as Enter at the end of the line would be
+    fn insert_line_below(&mut self) {
+        let indent = if self.auto_indent { calculate_auto_indent(&self.content[self.cursor_y]) } else { String::new() };
+        self.insert_line_at(self.cursor_y + 1, indent);
+    }
+
+    // Opens an empty line over the current one with the same indentation
+    fn insert_line_above(&mut self) {
+        let line = &self.content[self.cursor_y];
+        let indent = if self.auto_indent { line[..line.len() - line.trim_start().len()].to_string() } else { String::new() };
+        self.insert_line_at(self.cursor_y, indent);
+    }
+
+    fn insert_line_at(&mut self, line: usize, indent: String) {
+        self.unfold_lines(self.cursor_y, self.cursor_y);
+        self.shift_bookmarks_after_insert(line);
+        self.shift_folds_after_insert(line);
+        self.cursor_y = line;
+        self.cursor_x = indent.len();
+        self.content.insert(line, indent);
     }
 
     fn toggle_theme(&mut self) {
@@ -184,16 +334,374 @@ impl Editor {
         let _ = self.save_config();
     }
 
-    fn scroll_up(&mut self) {
-        self.scroll_position = self.scroll_position.saturating_sub(1);
+    fn toggle_trailing_whitespace(&mut self) {
+        self.show_trailing_whitespace = !self.show_trailing_whitespace;
+        let _ = self.save_config();
+    }
+
+    // The mouse wheel moves only the view, the cursor stays where it is even if it goes off screen
+    fn scroll_view_up(&mut self, lines: usize) {
+        for _ in 0..lines {
+            self.scroll_position = self.previous_visible_line(self.scroll_position as usize).unwrap_or(0) as u16;
+        }
         self.scroll_state = self.scroll_state.position(self.scroll_position as usize);
     }
 
-    fn scroll_down(&mut self) {
-        self.scroll_position = self.scroll_position.saturating_add(1);
+    fn scroll_view_down(&mut self, lines: usize) {
+        for _ in 0..lines {
+            if self.visible_lines().last() == Some(&(self.content.len() - 1)) {
+                break;
+            }
+            match self.next_visible_line(self.scroll_position as usize) {
+                Some(next) => self.scroll_position = next as u16,
+                None => break,
+            }
+        }
+        self.scroll_state = self.scroll_state.position(self.scroll_position as usize);
+    }
+
+    // Page up and down move the view and the cursor by the number of lines on screen
+    fn page_up(&mut self) {
+        for _ in 0..self.viewport_height.max(1) {
+            self.scroll_position = self.previous_visible_line(self.scroll_position as usize).unwrap_or(0) as u16;
+            self.cursor_y = self.previous_visible_line(self.cursor_y).unwrap_or(0);
+        }
+        self.cursor_x = self.cursor_x.min(self.content[self.cursor_y].len());
+        self.scroll_state = self.scroll_state.position(self.scroll_position as usize);
+    }
+
+    fn page_down(&mut self) {
+        for _ in 0..self.viewport_height.max(1) {
+            if let Some(next) = self.next_visible_line(self.cursor_y) {
+                self.cursor_y = next;
+            }
+            // Stop once the last line is on screen instead of scrolling into empty space
+            if self.visible_lines().last() != Some(&(self.content.len() - 1)) {
+                if let Some(next) = self.next_visible_line(self.scroll_position as usize) {
+                    self.scroll_position = next as u16;
+                }
+            }
+        }
+        self.cursor_x = self.cursor_x.min(self.content[self.cursor_y].len());
+        self.scroll_to_cursor();
+    }
+
+    fn goto_line(&mut self, line: usize) {
+        self.cursor_y = line.min(self.content.len().saturating_sub(1));
+        self.folds.retain(|&(start, end)| self.cursor_y <= start || self.cursor_y > end);
+        self.cursor_x = self.cursor_x.min(self.content[self.cursor_y].len());
+        // A jump to a far away line lands in the middle of the screen rather than on its edge
+        if self.visible_lines().contains(&self.cursor_y) {
+            self.scroll_to_cursor();
+        } else {
+            self.scroll_line_to(self.cursor_y, self.viewport_height / 2);
+        }
+    }
+
+    // Pressed repeatedly, moves the cursor line to the middle, then the top, then the bottom of the screen
+    fn center_cursor_in_viewport(&mut self) {
+        let rows_above = match self.recenter_count % 3 {
+            0 => self.viewport_height / 2,
+            1 => 0,
+            _ => self.viewport_height.saturating_sub(1),
+        };
+        self.scroll_line_to(self.cursor_y, rows_above);
+        self.recenter_count += 1;
+    }
+
+    // Scrolls so that line sits the given number of rows from the top, without leaving empty rows past the end of the file
+    fn scroll_line_to(&mut self, line: usize, rows_above: usize) {
+        let mut top = line;
+        for _ in 0..rows_above {
+            match self.previous_visible_line(top) {
+                Some(previous) => top = previous,
+                None => break,
+            }
+        }
+        while (top..self.content.len()).filter(|&index| !self.is_line_folded(index)).count() < self.viewport_height {
+            match self.previous_visible_line(top) {
+                Some(previous) => top = previous,
+                None => break,
+            }
+        }
+        self.scroll_position = top as u16;
         self.scroll_state = self.scroll_state.position(self.scroll_position as usize);
     }
 
+    fn goto_code_span(&mut self, code_span: &CodeSpan) {
+        // Code spans count lines and columns from 1
+        self.goto_line(code_span.start_line.saturating_sub(1));
+        self.cursor_x = code_span.start_column.saturating_sub(1).min(self.content[self.cursor_y].len());
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        if self.cursor_y < self.scroll_position as usize {
+            self.scroll_position = self.cursor_y as u16;
+        } else if self.viewport_height > 0 {
+            // Count rows rather than lines, folded lines take up no space on screen
+            let mut rows = (self.scroll_position as usize..=self.cursor_y).filter(|&line| !self.is_line_folded(line)).count();
+            while rows > self.viewport_height {
+                self.scroll_position = self.next_visible_line(self.scroll_position as usize).unwrap_or(self.cursor_y) as u16;
+                rows -= 1;
+            }
+        }
+        self.scroll_state = self.scroll_state.position(self.scroll_position as usize);
+    }
+
+    // Keeps the cursor column on screen when a line is wider than the editor
+    fn scroll_horizontally_to_cursor(&mut self) {
+        let horizontal_scroll = self.horizontal_scroll as usize;
+        if self.cursor_x < horizontal_scroll {
+            self.horizontal_scroll = self.cursor_x as u16;
+        } else if self.viewport_width > 0 && self.cursor_x >= horizontal_scroll + self.viewport_width {
+            self.horizontal_scroll = (self.cursor_x + 1 - self.viewport_width) as u16;
+        }
+    }
+
+    // Lines currently on screen, in order, starting at the scroll position and skipping folded lines
+    fn visible_lines(&self) -> Vec<usize> {
+        (self.scroll_position as usize..self.content.len()).filter(|&line| !self.is_line_folded(line)).take(self.viewport_height).collect()
+    }
+
+    // The row a line is drawn on, or None while it is folded away or scrolled out of view
+    fn screen_row(&self, line: usize) -> Option<usize> {
+        self.visible_lines().iter().position(|&visible| visible == line)
+    }
+
+    fn next_visible_line(&self, line: usize) -> Option<usize> {
+        (line + 1..self.content.len()).find(|&next| !self.is_line_folded(next))
+    }
+
+    fn previous_visible_line(&self, line: usize) -> Option<usize> {
+        (0..line).rev().find(|&previous| !self.is_line_folded(previous))
+    }
+
+    // The first line of a fold stays visible, only the lines after it are hidden
+    fn is_line_folded(&self, line: usize) -> bool {
+        self.folds.iter().any(|&(start, end)| line > start && line <= end)
+    }
+
+    fn fold_ending_at(&self, start_line: usize) -> Option<usize> {
+        self.folds.iter().find(|&&(start, _)| start == start_line).map(|&(_, end)| end)
+    }
+
+    fn find_closing_bracket(&self, line: usize, column: usize) -> Option<(usize, usize)> {
+        let mut depth = 0;
+        for (line_index, content_line) in self.content.iter().enumerate().skip(line) {
+            let start_column = if line_index == line { column } else { 0 };
+            for (column_index, c) in content_line.char_indices().filter(|&(index, _)| index >= start_column) {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some((line_index, column_index));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    fn toggle_fold(&mut self) {
+        if let Some(index) = self.folds.iter().position(|&(start, _)| start == self.cursor_y) {
+            self.folds.remove(index);
+            return;
+        }
+
+        // Fold the first block opened on this line that closes on a later line
+        let block_end = self.content[self.cursor_y].match_indices('{').find_map(|(column, _)| self.find_closing_bracket(self.cursor_y, column).filter(|&(end_line, _)| end_line > self.cursor_y));
+
+        if let Some((end_line, _)) = block_end {
+            // Folds nested inside the new one would be hidden anyway
+            self.folds.retain(|&(start, end)| start < self.cursor_y || end > end_line);
+            let index = self.folds.partition_point(|&(start, _)| start < self.cursor_y);
+            self.folds.insert(index, (self.cursor_y, end_line));
+        }
+    }
+
+    // Editing inside or next to a folded block unfolds it, the block extent may no longer be valid
+    fn unfold_lines(&mut self, first_line: usize, last_line: usize) {
+        self.folds.retain(|&(start, end)| start > last_line || end < first_line);
+    }
+
+    fn shift_folds_after_insert(&mut self, first_moved_line: usize) {
+        self.folds.iter_mut().filter(|(start, _)| *start >= first_moved_line).for_each(|(start, end)| {
+            *start += 1;
+            *end += 1;
+        });
+    }
+
+    fn shift_folds_after_removal(&mut self, removed_line: usize) {
+        self.folds.iter_mut().filter(|(start, _)| *start > removed_line).for_each(|(start, end)| {
+            *start -= 1;
+            *end -= 1;
+        });
+    }
+
+    fn toggle_bookmark(&mut self) {
+        match self.bookmarks.binary_search(&self.cursor_y) {
+            Ok(index) => {
+                self.bookmarks.remove(index);
+            }
+            Err(index) => self.bookmarks.insert(index, self.cursor_y),
+        }
+    }
+
+    fn next_bookmark(&mut self) {
+        let next = self.bookmarks.iter().find(|&&line| line > self.cursor_y).or(self.bookmarks.first());
+        if let Some(&line) = next {
+            self.goto_line(line);
+        }
+    }
+
+    fn previous_bookmark(&mut self) {
+        let previous = self.bookmarks.iter().rev().find(|&&line| line < self.cursor_y).or(self.bookmarks.last());
+        if let Some(&line) = previous {
+            self.goto_line(line);
+        }
+    }
+
+    fn shift_bookmarks_after_insert(&mut self, first_moved_line: usize) {
+        self.bookmarks.iter_mut().filter(|line| **line >= first_moved_line).for_each(|line| *line += 1);
+    }
+
+    // The joined line's text ends up on the line above it, so its bookmark moves up with it
+    fn shift_bookmarks_after_join(&mut self, joined_line: usize) {
+        self.bookmarks.iter_mut().filter(|line| **line >= joined_line).for_each(|line| *line -= 1);
+        self.bookmarks.dedup();
+    }
+
+    fn toggle_diagnostics(&mut self) {
+        self.dialog_mode = if self.dialog_mode == DialogMode::Diagnostics { DialogMode::None } else { DialogMode::Diagnostics };
+        self.diagnostics_index = 0;
+    }
+
+    fn select_next_diagnostic(&mut self) {
+        if self.diagnostics_index + 1 < self.code_errors.len() {
+            self.diagnostics_index += 1;
+        }
+    }
+
+    fn select_previous_diagnostic(&mut self) {
+        self.diagnostics_index = self.diagnostics_index.saturating_sub(1);
+    }
+
+    fn open_selected_diagnostic(&mut self) {
+        if let Some(error) = self.code_errors.get(self.diagnostics_index).cloned() {
+            self.goto_code_span(&error.code_span);
+        }
+        self.dialog_mode = DialogMode::None;
+    }
+
+    fn goto_next_diagnostic(&mut self) {
+        if self.code_errors.is_empty() {
+            self.flash_status("No errors");
+            return;
+        }
+        let index = self.current_diagnostic.map_or(0, |index| (index + 1) % self.code_errors.len());
+        self.goto_diagnostic(index);
+    }
+
+    fn goto_previous_diagnostic(&mut self) {
+        if self.code_errors.is_empty() {
+            self.flash_status("No errors");
+            return;
+        }
+        let error_count = self.code_errors.len();
+        let index = self.current_diagnostic.map_or(error_count - 1, |index| (index.min(error_count) + error_count - 1) % error_count);
+        self.goto_diagnostic(index);
+    }
+
+    fn goto_diagnostic(&mut self, index: usize) {
+        let code_span = self.code_errors[index].code_span.clone();
+        self.goto_code_span(&code_span);
+        self.current_diagnostic = Some(index);
+    }
+
+    fn toggle_build_output(&mut self) {
+        self.dialog_mode = if self.dialog_mode == DialogMode::BuildOutput { DialogMode::None } else { DialogMode::BuildOutput };
+    }
+
+    fn scroll_build_output_down(&mut self, lines: u16) {
+        let last_line = self.build_output.len().saturating_sub(1) as u16;
+        self.build_output_scroll = (self.build_output_scroll + lines).min(last_line);
+    }
+
+    fn scroll_build_output_up(&mut self, lines: u16) {
+        self.build_output_scroll = self.build_output_scroll.saturating_sub(lines);
+    }
+
+    fn toggle_transpile_preview(&mut self) {
+        self.show_transpile_preview = !self.show_transpile_preview;
+    }
+
+    fn scroll_transpile_preview_down(&mut self) {
+        let last_line = self.transpile_preview.len().saturating_sub(1) as u16;
+        self.transpile_preview_scroll = (self.transpile_preview_scroll + 1).min(last_line);
+    }
+
+    fn scroll_transpile_preview_up(&mut self) {
+        self.transpile_preview_scroll = self.transpile_preview_scroll.saturating_sub(1);
+    }
+
+    fn open_command_palette(&mut self) {
+        self.dialog_mode = DialogMode::CommandPalette;
+        self.command_palette_query.clear();
+        self.command_palette_index = 0;
+    }
+
+    fn command_for_key(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<EditorCommand> {
+        let pressed = KeyChord::new(code, modifiers);
+        self.keybindings.iter().find(|(_, chord)| *chord == pressed).map(|(command, _)| *command)
+    }
+
+    fn keybinding_for(&self, command: EditorCommand) -> Option<KeyChord> {
+        self.keybindings.iter().find(|(bound, _)| *bound == command).map(|(_, chord)| *chord)
+    }
+
+    fn select_next_command(&mut self) {
+        if self.command_palette_index + 1 < matching_commands(&self.command_palette_query).len() {
+            self.command_palette_index += 1;
+        }
+    }
+
+    fn select_previous_command(&mut self) {
+        self.command_palette_index = self.command_palette_index.saturating_sub(1);
+    }
+
+    // Chords like Ctrl+P arrive as plain characters with a modifier, they aren't part of the query
+    fn edit_command_palette_query(&mut self, key_code: KeyCode, modifiers: KeyModifiers) {
+        match key_code {
+            KeyCode::Char(c) if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => self.command_palette_query.push(c),
+            KeyCode::Backspace => {
+                self.command_palette_query.pop();
+            }
+            _ => return,
+        }
+        self.command_palette_index = 0;
+    }
+
+    // Source errors first, then whatever the Rust compiler reported on the last build
+    fn set_code_errors(&mut self, errors: Vec<CodeError>) {
+        self.code_errors = errors;
+        self.code_errors.extend(self.compile_errors.iter().cloned());
+    }
+
+    // Swaps out only the compiler's errors, the lex thread may have written new source errors while cargo ran
+    fn set_compile_errors(&mut self, errors: Vec<CodeError>) {
+        let source_error_count = self.code_errors.len().saturating_sub(self.compile_errors.len());
+        self.code_errors.truncate(source_error_count);
+        self.code_errors.extend(errors.iter().cloned());
+        self.compile_errors = errors;
+    }
+
+    fn flash_status(&mut self, message: &str) {
+        self.status_message = Some((message.to_string(), Instant::now()));
+    }
+
     fn next_tab(&mut self) {
         self.tab_index = (self.tab_index + 1) % 4; // Assuming 4 tabs
     }
@@ -219,27 +727,93 @@ impl Editor {
             }
         );
 
-        file.write_all(theme.as_bytes())?;
+        writeln!(file, "{}", theme)?;
+        writeln!(file, "debounce_ms={}", self.debounce.as_millis())?;
+        if let Some(max_line_length) = self.max_line_length {
+            writeln!(file, "max_line_length={}", max_line_length)?;
+        }
+        writeln!(file, "auto_indent={}", self.auto_indent)?;
+        writeln!(file, "show_trailing_whitespace={}", self.show_trailing_whitespace)?;
+        writeln!(file, "rulers={}", self.rulers.iter().map(|column| column.to_string()).collect::<Vec<_>>().join(","))?;
+        write!(file, "{}", keybindings_to_config(&self.keybindings))?;
         Ok(())
     }
 
-    fn load_config() -> String {
+    fn read_config() -> Option<String> {
         let home_dir = env::current_dir().expect("Could not get the directory that is running Nail to save configuration");
         let config_path = PathBuf::from(home_dir).join(".nail");
 
         // Debugging print to check file path
         log::info!("Loading configuration from {:?}", config_path);
 
-        if let Ok(config_data) = fs::read_to_string(&config_path) {
-            for line in config_data.lines() {
-                if line.starts_with("theme=") {
-                    return line["theme=".len()..].to_string();
-                }
-            }
+        fs::read_to_string(&config_path).ok()
+    }
+
+    fn load_config(key: &str) -> Option<String> {
+        let config_data = Self::read_config()?;
+        config_data.lines().find_map(|line| line.strip_prefix(key)?.strip_prefix('=')).map(|value| value.trim().to_string())
+    }
+}
+
+const INDENT: &str = "    ";
+
+// The indentation for a new line split from `line_before_cursor`: one level deeper after an opening brace,
+// one level shallower after a statement that closes a block, otherwise the same
+fn calculate_auto_indent(line_before_cursor: &str) -> String {
+    let indent = &line_before_cursor[..line_before_cursor.len() - line_before_cursor.trim_start().len()];
+    let code = line_before_cursor.trim();
+    if code.ends_with('{') {
+        format!("{}{}", indent, INDENT)
+    } else if code.ends_with('}') && !code.starts_with('}') && code.matches('}').count() > code.matches('{').count() {
+        // A line that starts with the brace was dedented when the brace was typed
+        indent.strip_suffix(INDENT).unwrap_or("").to_string()
+    } else {
+        indent.to_string()
+    }
+}
+
+// A word is a run of letters, digits and underscores, or a run of other symbols
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Byte index where the word before `x` starts, skipping any whitespace right before `x`
+fn find_prev_word_boundary(line: &str, x: usize) -> usize {
+    let chars: Vec<(usize, char)> = line[..x].char_indices().collect();
+    let mut index = chars.len();
+    while index > 0 && chars[index - 1].1.is_whitespace() {
+        index -= 1;
+    }
+    if let Some(&(_, last)) = index.checked_sub(1).and_then(|last| chars.get(last)) {
+        while index > 0 && !chars[index - 1].1.is_whitespace() && is_word_char(chars[index - 1].1) == is_word_char(last) {
+            index -= 1;
         }
+    }
+    chars.get(index).map_or(x, |&(byte_index, _)| byte_index)
+}
 
-        "dark".to_string()
+// Byte index where the word after `x` ends, skipping any whitespace right after `x`
+fn find_next_word_boundary(line: &str, x: usize) -> usize {
+    let mut chars = line[x..].char_indices().skip_while(|(_, c)| c.is_whitespace()).peekable();
+    let Some(&(_, first)) = chars.peek() else {
+        return line.len();
+    };
+    chars.find(|&(_, c)| c.is_whitespace() || is_word_char(c) != is_word_char(first)).map_or(line.len(), |(offset, _)| x + offset)
+}
+
+// `nail --emit-project <file.nail> <output dir>` writes a buildable Cargo project without starting the editor
+fn emit_project_from_file(source_path: &str, output_dir: &str) -> Result<(), Vec<CodeError>> {
+    let source = fs::read_to_string(source_path).map_err(|e| vec![CodeError::from(format!("Failed to read {}: {}", source_path, e))])?;
+    let tokens = lexer::lexer(&source);
+
+    let lexing_errors = lexing_errors(&tokens);
+    if !lexing_errors.is_empty() {
+        return Err(lexing_errors);
     }
+
+    let mut ast = parser::parse(tokens).map_err(|e| vec![e])?;
+    checker::checker(&mut ast)?;
+    transpilier::emit_project(&ast, Path::new(output_dir)).map_err(|e| vec![CodeError::from(format!("Failed to write project to {}: {}", output_dir, e))])
 }
 
 fn main() -> Result<(), io::Error> {
@@ -252,6 +826,24 @@ fn main() -> Result<(), io::Error> {
         error!("Backtrace:\n{:?}", backtrace);
     }));
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "--emit-project") {
+        let (Some(source_path), Some(output_dir)) = (args.get(2), args.get(3)) else {
+            eprintln!("Usage: {} --emit-project <file.nail> <output dir>", args[0]);
+            std::process::exit(2);
+        };
+        match emit_project_from_file(source_path, output_dir) {
+            Ok(()) => println!("Wrote Cargo project to {}", output_dir),
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}:{}:{}: {}", source_path, error.code_span.start_line, error.code_span.start_column, error.message);
+                }
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     let (tx_resize, rx_resize) = channel::<EditorMessage>();
     let (tx_draw, rx_draw) = channel::<EditorMessage>();
     let (tx_key, rx_key) = channel::<EditorMessage>();
@@ -266,7 +858,21 @@ fn main() -> Result<(), io::Error> {
     let mut terminal = Terminal::new(backend)?;
 
     let mut editor = Editor::new();
-    let theme = Editor::load_config();
+    editor.keybindings = keybindings_from_config(&Editor::read_config().unwrap_or_default());
+    if let Some(debounce_ms) = Editor::load_config("debounce_ms").and_then(|value| value.parse().ok()) {
+        editor.debounce = Duration::from_millis(debounce_ms);
+    }
+    if let Some(auto_indent) = Editor::load_config("auto_indent").and_then(|value| value.parse().ok()) {
+        editor.auto_indent = auto_indent;
+    }
+    if let Some(show_trailing_whitespace) = Editor::load_config("show_trailing_whitespace").and_then(|value| value.parse().ok()) {
+        editor.show_trailing_whitespace = show_trailing_whitespace;
+    }
+    editor.max_line_length = Editor::load_config("max_line_length").and_then(|value| value.parse().ok());
+    if let Some(rulers) = Editor::load_config("rulers") {
+        editor.rulers = rulers.split(',').filter_map(|column| column.trim().parse().ok()).collect();
+    }
+    let theme = Editor::load_config("theme").unwrap_or_else(|| "dark".to_string());
     editor.set_theme(&theme);
 
     let editor_arc = Arc::new(Mutex::new(editor));
@@ -344,3 +950,228 @@ fn main() -> Result<(), io::Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with(content: &[&str], cursor_x: usize, cursor_y: usize) -> Editor {
+        let mut editor = Editor::new();
+        editor.content = content.iter().map(|line| line.to_string()).collect();
+        editor.cursor_x = cursor_x;
+        editor.cursor_y = cursor_y;
+        editor
+    }
+
+    #[test]
+    fn test_bookmarks_follow_inserted_lines() {
+        let mut editor = editor_with(&["c first:i = 1;", "c second:i = 2;"], 0, 1);
+        editor.toggle_bookmark();
+        editor.cursor_y = 0;
+        editor.insert_line_below();
+        assert_eq!(editor.bookmarks, vec![2]);
+    }
+
+    #[test]
+    fn test_bookmarks_follow_joined_lines() {
+        // Joining a line above the bookmark moves it up
+        let mut editor = editor_with(&["c first:i = 1;", "", "c second:i = 2;"], 0, 2);
+        editor.toggle_bookmark();
+        editor.cursor_y = 1;
+        editor.delete_char();
+        assert_eq!(editor.content, vec!["c first:i = 1;", "c second:i = 2;"]);
+        assert_eq!(editor.bookmarks, vec![1]);
+
+        // Joining the bookmarked line into the one above keeps the bookmark on the joined line
+        (editor.cursor_x, editor.cursor_y) = (0, 1);
+        editor.delete_char();
+        assert_eq!(editor.content, vec!["c first:i = 1;c second:i = 2;"]);
+        assert_eq!(editor.bookmarks, vec![0]);
+    }
+
+    fn folded_editor() -> Editor {
+        let mut editor = editor_with(&["fn main():v {", "    c x:i = 1;", "    c y:i = 2;", "}", "c z:i = 3;"], 0, 0);
+        editor.toggle_fold();
+        editor
+    }
+
+    #[test]
+    fn test_toggle_fold_on_an_opening_brace() {
+        let mut editor = folded_editor();
+        assert_eq!(editor.folds, vec![(0, 3)]);
+        assert!(!editor.is_line_folded(0));
+        assert!((1..=3).all(|line| editor.is_line_folded(line)));
+        editor.toggle_fold();
+        assert!(editor.folds.is_empty());
+
+        // A line without a block has nothing to fold
+        editor.cursor_y = 4;
+        editor.toggle_fold();
+        assert!(editor.folds.is_empty());
+    }
+
+    #[test]
+    fn test_screen_row_skips_folded_lines() {
+        let mut editor = folded_editor();
+        editor.viewport_height = 5;
+        assert_eq!(editor.screen_row(0), Some(0));
+        assert_eq!(editor.screen_row(2), None);
+        assert_eq!(editor.screen_row(4), Some(1));
+        editor.scroll_position = 4;
+        assert_eq!(editor.screen_row(0), None);
+        assert_eq!(editor.screen_row(4), Some(0));
+    }
+
+    #[test]
+    fn test_cursor_skips_folded_lines() {
+        let mut editor = folded_editor();
+        editor.move_cursor_down();
+        assert_eq!(editor.cursor_y, 4);
+        editor.move_cursor_up();
+        assert_eq!(editor.cursor_y, 0);
+    }
+
+    #[test]
+    fn test_editing_a_folded_block_unfolds_it() {
+        let mut editor = folded_editor();
+        editor.cursor_x = 13;
+        editor.insert_char(' ');
+        assert!(editor.folds.is_empty());
+
+        let mut editor = folded_editor();
+        (editor.cursor_x, editor.cursor_y) = (4, 2);
+        editor.delete_char();
+        assert_eq!(editor.content[2], "   c y:i = 2;");
+        assert!(editor.folds.is_empty());
+    }
+
+    #[test]
+    fn test_command_palette_query_ignores_modifier_chords() {
+        let mut editor = editor_with(&[""], 0, 0);
+        editor.edit_command_palette_query(KeyCode::Char('b'), KeyModifiers::NONE);
+        editor.edit_command_palette_query(KeyCode::Char('U'), KeyModifiers::SHIFT);
+        editor.edit_command_palette_query(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        editor.edit_command_palette_query(KeyCode::Char('x'), KeyModifiers::ALT);
+        assert_eq!(editor.command_palette_query, "bU");
+        editor.edit_command_palette_query(KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(editor.command_palette_query, "b");
+    }
+
+    #[test]
+    fn test_auto_indent() {
+        assert_eq!(calculate_auto_indent("    if { x > 1 => {"), "        ");
+        assert_eq!(calculate_auto_indent("        r x; }"), "    ");
+        assert_eq!(calculate_auto_indent("    c x:i = 1;"), "    ");
+        assert_eq!(calculate_auto_indent("c x:i = 1;"), "");
+    }
+
+    #[test]
+    fn test_closing_brace_dedents_the_line() {
+        let mut editor = editor_with(&["fn main():v {", "    if { x > 1 => {", "        "], 8, 2);
+        editor.insert_char('}');
+        assert_eq!(editor.content[2], "    }");
+        assert_eq!(editor.cursor_x, 5);
+        editor.insert_newline();
+        assert_eq!(editor.content[3], "    ");
+
+        // Only a brace that starts the line is moved
+        let mut editor = editor_with(&["    r x; "], 9, 0);
+        editor.insert_char('}');
+        assert_eq!(editor.content[0], "    r x; }");
+    }
+
+    #[test]
+    fn test_newline_keeps_the_indentation() {
+        let mut editor = editor_with(&["    c x:i = 1; c y:i = 2;"], 14, 0);
+        editor.insert_newline();
+        assert_eq!(editor.content, vec!["    c x:i = 1;", "    c y:i = 2;"]);
+        assert_eq!((editor.cursor_x, editor.cursor_y), (4, 1));
+    }
+
+    #[test]
+    fn test_insert_line_below_and_above() {
+        let mut editor = editor_with(&["fn main():v {", "    c x:i = 1;", "}"], 6, 1);
+        editor.insert_line_below();
+        assert_eq!(editor.content, vec!["fn main():v {", "    c x:i = 1;", "    ", "}"]);
+        assert_eq!((editor.cursor_x, editor.cursor_y), (4, 2));
+
+        let mut editor = editor_with(&["fn main():v {", "    c x:i = 1;", "}"], 6, 1);
+        editor.insert_line_above();
+        assert_eq!(editor.content, vec!["fn main():v {", "    ", "    c x:i = 1;", "}"]);
+        assert_eq!((editor.cursor_x, editor.cursor_y), (4, 1));
+
+        let mut editor = editor_with(&["fn main():v {", "}"], 0, 0);
+        editor.insert_line_below();
+        assert_eq!(editor.content, vec!["fn main():v {", "    ", "}"]);
+    }
+
+    #[test]
+    fn test_wheel_scrolling_leaves_the_cursor_alone() {
+        let lines: Vec<String> = (0..10).map(|line| line.to_string()).collect();
+        let mut editor = editor_with(&lines.iter().map(String::as_str).collect::<Vec<_>>(), 0, 0);
+        editor.viewport_height = 4;
+
+        editor.scroll_view_down(3);
+        assert_eq!((editor.scroll_position, editor.cursor_y), (3, 0));
+        editor.scroll_view_down(30);
+        assert_eq!(editor.scroll_position, 6); // stops with the last line at the bottom
+        editor.scroll_view_up(30);
+        assert_eq!((editor.scroll_position, editor.cursor_y), (0, 0));
+    }
+
+    #[test]
+    fn test_delete_word_in_the_middle_of_a_line() {
+        let mut editor = editor_with(&["c total:i = first + second;"], 18, 0);
+        editor.delete_word_backward();
+        assert_eq!(editor.content, vec!["c total:i = + second;"]);
+        assert_eq!(editor.cursor_x, 12);
+
+        editor.delete_word_forward();
+        assert_eq!(editor.content, vec!["c total:i =  second;"]);
+        editor.delete_word_forward();
+        assert_eq!(editor.content, vec!["c total:i = ;"]);
+        assert_eq!(editor.cursor_x, 12);
+    }
+
+    #[test]
+    fn test_delete_word_across_a_line_boundary() {
+        let mut editor = editor_with(&["first", "second"], 0, 1);
+        editor.delete_word_backward();
+        assert_eq!(editor.content, vec!["firstsecond"]);
+        assert_eq!(editor.cursor_x, 5);
+
+        let mut editor = editor_with(&["first", "second"], 5, 0);
+        editor.delete_word_forward();
+        assert_eq!(editor.content, vec!["firstsecond"]);
+        assert_eq!(editor.cursor_x, 5);
+    }
+
+    #[test]
+    fn test_transpose_chars_mid_line() {
+        let mut editor = editor_with(&["teh cat"], 2, 0);
+        editor.transpose_chars();
+        assert_eq!(editor.content, vec!["the cat"]);
+        assert_eq!(editor.cursor_x, 3);
+    }
+
+    #[test]
+    fn test_transpose_chars_at_line_edges() {
+        let mut editor = editor_with(&["abdc"], 4, 0);
+        editor.transpose_chars();
+        assert_eq!(editor.content, vec!["abcd"]);
+        assert_eq!(editor.cursor_x, 4);
+
+        let mut editor = editor_with(&["abc"], 0, 0);
+        editor.transpose_chars();
+        assert_eq!(editor.content, vec!["abc"]);
+        assert_eq!(editor.cursor_x, 0);
+    }
+
+    #[test]
+    fn test_transpose_chars_multibyte() {
+        let mut editor = editor_with(&["aé"], 3, 0);
+        editor.transpose_chars();
+        assert_eq!(editor.content, vec!["éa"]);
+        assert_eq!(editor.cursor_x, 3);
+    }
+}
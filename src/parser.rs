@@ -8,27 +8,144 @@ use std::vec::IntoIter;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ASTNode {
-    Program { statements: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    FunctionDeclaration { name: String, params: Vec<(String, NailDataTypeDescriptor)>, data_type: NailDataTypeDescriptor, body: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    LambdaDeclaration { params: Vec<(String, NailDataTypeDescriptor)>, data_type: NailDataTypeDescriptor, body: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    FunctionCall { name: String, args: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    VariableDeclaration { name: String, data_type: NailDataTypeDescriptor, value: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    ConstDeclaration { name: String, data_type: NailDataTypeDescriptor, value: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    IfStatement { condition_branches: Vec<(Box<ASTNode>, Box<ASTNode>)>, else_branch: Option<Box<ASTNode>>, code_span: CodeSpan, scope: usize },
-    Block { statements: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    BinaryOperation { left: Box<ASTNode>, operator: Operation, right: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    UnaryOperation { operator: Operation, operand: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    StructDeclaration { name: String, fields: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    StructDeclarationField { name: String, data_type: NailDataTypeDescriptor },
-    StructInstantiation { name: String, fields: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    StructInstantiationField { name: String, value: Box<ASTNode>, code_span: CodeSpan, scope: usize },
-    EnumDeclaration { name: String, variants: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    EnumVariant { name: String, variant: String, code_span: CodeSpan, scope: usize },
-    ArrayLiteral { elements: Vec<ASTNode>, code_span: CodeSpan, scope: usize },
-    Identifier { name: String, code_span: CodeSpan, scope: usize },
-    NumberLiteral { value: String, data_type: NailDataTypeDescriptor, code_span: CodeSpan, scope: usize },
-    StringLiteral { value: String, code_span: CodeSpan, scope: usize },
-    ReturnDeclaration { statement: Box<ASTNode>, code_span: CodeSpan, scope: usize },
+    Program {
+        statements: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    FunctionDeclaration {
+        name: String,
+        name_span: CodeSpan,
+        params: Vec<(String, NailDataTypeDescriptor)>,
+        param_type_spans: Vec<CodeSpan>,
+        data_type: NailDataTypeDescriptor,
+        type_span: CodeSpan,
+        body: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    LambdaDeclaration {
+        params: Vec<(String, NailDataTypeDescriptor)>,
+        param_type_spans: Vec<CodeSpan>,
+        data_type: NailDataTypeDescriptor,
+        type_span: CodeSpan,
+        body: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    FunctionCall {
+        name: String,
+        args: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    VariableDeclaration {
+        name: String,
+        data_type: NailDataTypeDescriptor,
+        type_span: CodeSpan,
+        value: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    ConstDeclaration {
+        name: String,
+        data_type: NailDataTypeDescriptor,
+        type_span: CodeSpan,
+        value: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    IfStatement {
+        condition_branches: Vec<(Box<ASTNode>, Box<ASTNode>)>,
+        else_branch: Option<Box<ASTNode>>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    Block {
+        statements: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    BinaryOperation {
+        left: Box<ASTNode>,
+        operator: Operation,
+        right: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    UnaryOperation {
+        operator: Operation,
+        operand: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    StructDeclaration {
+        name: String,
+        fields: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    StructDeclarationField {
+        name: String,
+        data_type: NailDataTypeDescriptor,
+        type_span: CodeSpan,
+        code_span: CodeSpan,
+    },
+    StructInstantiation {
+        name: String,
+        fields: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    StructInstantiationField {
+        name: String,
+        value: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    EnumDeclaration {
+        name: String,
+        variants: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    EnumVariant {
+        name: String,
+        variant: String,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    ArrayLiteral {
+        elements: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    Identifier {
+        name: String,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    NumberLiteral {
+        value: String,
+        data_type: NailDataTypeDescriptor,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    StringLiteral {
+        value: String,
+        code_span: CodeSpan,
+        scope: usize,
+    },
+    StringInterpolation {
+        parts: Vec<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    }, // text as StringLiterals between the embedded expressions
+    ReturnDeclaration {
+        statement: Box<ASTNode>,
+        code_span: CodeSpan,
+        scope: usize,
+    },
 }
 
 impl Default for ASTNode {
@@ -37,14 +154,44 @@ impl Default for ASTNode {
     }
 }
 
+impl ASTNode {
+    pub fn code_span(&self) -> &CodeSpan {
+        match self {
+            ASTNode::Program { code_span, .. }
+            | ASTNode::FunctionDeclaration { code_span, .. }
+            | ASTNode::LambdaDeclaration { code_span, .. }
+            | ASTNode::FunctionCall { code_span, .. }
+            | ASTNode::VariableDeclaration { code_span, .. }
+            | ASTNode::ConstDeclaration { code_span, .. }
+            | ASTNode::IfStatement { code_span, .. }
+            | ASTNode::Block { code_span, .. }
+            | ASTNode::BinaryOperation { code_span, .. }
+            | ASTNode::UnaryOperation { code_span, .. }
+            | ASTNode::StructDeclaration { code_span, .. }
+            | ASTNode::StructDeclarationField { code_span, .. }
+            | ASTNode::StructInstantiation { code_span, .. }
+            | ASTNode::StructInstantiationField { code_span, .. }
+            | ASTNode::EnumDeclaration { code_span, .. }
+            | ASTNode::EnumVariant { code_span, .. }
+            | ASTNode::ArrayLiteral { code_span, .. }
+            | ASTNode::Identifier { code_span, .. }
+            | ASTNode::NumberLiteral { code_span, .. }
+            | ASTNode::StringLiteral { code_span, .. }
+            | ASTNode::StringInterpolation { code_span, .. }
+            | ASTNode::ReturnDeclaration { code_span, .. } => code_span,
+        }
+    }
+}
+
 pub struct ParserState {
     tokens: Peekable<IntoIter<Token>>,
     current_token: Option<Token>,
     previous_token: Option<Token>,
+    in_interpolation: bool, // the tokens of one `{expression}` in a string, which end without a `;`
 }
 
 pub fn parse(tokens: Vec<Token>) -> Result<ASTNode, CodeError> {
-    let mut state = ParserState { tokens: tokens.into_iter().peekable(), current_token: None, previous_token: None };
+    let mut state = ParserState { tokens: tokens.into_iter().peekable(), current_token: None, previous_token: None, in_interpolation: false };
     parse_inner(&mut state)
 }
 
@@ -89,6 +236,10 @@ fn parse_primary(state: &mut ParserState) -> Result<ASTNode, CodeError> {
                 advance(state);
                 Ok(ASTNode::StringLiteral { value, code_span: token.code_span, scope: NO_SCOPE })
             }
+            TokenType::InterpolatedString(part_tokens) => {
+                advance(state);
+                parse_string_interpolation(part_tokens, token.code_span)
+            }
             TokenType::ParenthesisOpen => {
                 advance(state);
                 let expr = parse_expression(state, 0)?;
@@ -111,6 +262,25 @@ fn parse_primary(state: &mut ParserState) -> Result<ASTNode, CodeError> {
     }
 }
 
+fn parse_string_interpolation(part_tokens: Vec<Token>, code_span: CodeSpan) -> Result<ASTNode, CodeError> {
+    let mut parts = Vec::new();
+    for part in part_tokens {
+        match part.token_type {
+            TokenType::StringLiteral(value) => parts.push(ASTNode::StringLiteral { value, code_span: part.code_span, scope: NO_SCOPE }),
+            TokenType::Interpolation(expression_tokens) => {
+                // Each embedded expression has its own tokens, so it gets its own parser state
+                let mut expression_state = ParserState { tokens: expression_tokens.into_iter().peekable(), current_token: None, previous_token: None, in_interpolation: true };
+                parts.push(parse_expression(&mut expression_state, 0)?);
+                if let Some(token) = expression_state.tokens.peek() {
+                    return Err(CodeError { message: format!("Unexpected token {:?} in string interpolation", token.token_type), code_span: token.code_span.clone() });
+                }
+            }
+            _ => return Err(CodeError { message: format!("Unexpected token in string interpolation: {:?}", part.token_type), code_span: part.code_span }),
+        }
+    }
+    Ok(ASTNode::StringInterpolation { parts, code_span, scope: NO_SCOPE })
+}
+
 fn parse_statement(state: &mut ParserState) -> Result<ASTNode, CodeError> {
     match state.tokens.peek() {
         Some(token) => match &token.token_type {
@@ -166,6 +336,21 @@ fn expect_token(state: &mut ParserState, expected: TokenType) -> Result<CodeSpan
     }
 }
 
+// A missing `;` is reported right after the end of the statement, not on whatever token follows it
+fn expect_end_of_statement(state: &mut ParserState) -> Result<CodeSpan, CodeError> {
+    if matches!(state.tokens.peek().map(|token| &token.token_type), Some(TokenType::EndStatementOrExpression)) {
+        return expect_token(state, TokenType::EndStatementOrExpression);
+    }
+
+    let statement_end = state.current_token.as_ref().map_or(CodeSpan::default(), |token| token.code_span.clone());
+    let error = CodeError {
+        message: "Expected ';' after statement".to_string(),
+        code_span: CodeSpan { start_line: statement_end.end_line, start_column: statement_end.end_column, end_line: statement_end.end_line, end_column: statement_end.end_column + 1 },
+    };
+    log::error!("Expect end of statement error: {:?}", error);
+    Err(error)
+}
+
 fn expect_identifier(state: &mut ParserState) -> Result<String, CodeError> {
     if let Some(Token { token_type: TokenType::Identifier(name), .. }) = advance(state) {
         Ok(name)
@@ -193,18 +378,20 @@ fn parse_function_call(state: &mut ParserState, name: String) -> Result<ASTNode,
     let code_span = expect_token(state, TokenType::ParenthesisClose)?;
 
     // it should have a ; if the next token after is not a ) for stuff like fun(yay(times)); so it doesnt need a bunch of ugly ; like fun(yay(times););
-    if state.tokens.peek().map_or(true, |t| t.token_type != TokenType::ParenthesisClose) {
-        let _ = expect_token(state, TokenType::EndStatementOrExpression)?;
+    if !state.in_interpolation && state.tokens.peek().map_or(true, |t| t.token_type != TokenType::ParenthesisClose) {
+        let _ = expect_end_of_statement(state)?;
     }
 
     Ok(ASTNode::FunctionCall { name, args, code_span, scope: NO_SCOPE })
 }
 
 fn parse_lambda_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError> {
-    if let Some(Token { token_type: TokenType::LambdaSignature(tokens), .. }) = advance(state) {
-        let code_span = state.previous_token.as_ref().map(|t| t.code_span.clone()).unwrap_or(CodeSpan::default());
+    if let Some(Token { token_type: TokenType::LambdaSignature(tokens), code_span }) = advance(state) {
         let mut lambda_tokens = tokens.into_iter();
         let mut params = Vec::new();
+        let mut param_type_spans = Vec::new();
+        #[allow(unused_assignments)]
+        let mut type_span = CodeSpan::default();
         #[allow(unused_assignments)]
         let mut data_type = NailDataTypeDescriptor::Void;
 
@@ -212,14 +399,16 @@ fn parse_lambda_declaration(state: &mut ParserState) -> Result<ASTNode, CodeErro
         loop {
             match lambda_tokens.next() {
                 Some(Token { token_type: TokenType::Identifier(param_name), .. }) => {
-                    if let Some(Token { token_type: TokenType::TypeDeclaration(type_desc), .. }) = lambda_tokens.next() {
+                    if let Some(Token { token_type: TokenType::TypeDeclaration(type_desc), code_span: param_type_span }) = lambda_tokens.next() {
                         params.push((param_name.clone(), type_desc.clone()));
+                        param_type_spans.push(param_type_span);
 
                         // Check for comma or end of parameters
                         match lambda_tokens.next() {
                             Some(Token { token_type: TokenType::Comma, .. }) => continue,
-                            Some(Token { token_type: TokenType::LambdaReturnTypeDeclaration(rt), .. }) => {
+                            Some(Token { token_type: TokenType::LambdaReturnTypeDeclaration(rt), code_span: return_type_span }) => {
                                 data_type = rt;
+                                type_span = return_type_span;
                                 break;
                             }
                             Some(other) => return Err(CodeError { message: format!("Expected comma or return type declaration, found {:?}", other.token_type), code_span: other.code_span.clone() }),
@@ -237,8 +426,9 @@ fn parse_lambda_declaration(state: &mut ParserState) -> Result<ASTNode, CodeErro
                         });
                     }
                 }
-                Some(Token { token_type: TokenType::LambdaReturnTypeDeclaration(rt), .. }) => {
+                Some(Token { token_type: TokenType::LambdaReturnTypeDeclaration(rt), code_span: return_type_span }) => {
                     data_type = rt;
+                    type_span = return_type_span;
                     break;
                 }
                 Some(other) => return Err(CodeError { message: format!("Unexpected token in lambda declaration: {:?}", other.token_type), code_span: other.code_span.clone() }),
@@ -254,22 +444,21 @@ fn parse_lambda_declaration(state: &mut ParserState) -> Result<ASTNode, CodeErro
         // Parse the lambda body
         let body = Box::new(parse_block(state)?);
 
-        Ok(ASTNode::LambdaDeclaration { params, data_type, body, code_span, scope: NO_SCOPE })
+        Ok(ASTNode::LambdaDeclaration { params, param_type_spans, data_type, type_span, body, code_span, scope: NO_SCOPE })
     } else {
         Err(CodeError { message: "Expected lambda declaration".to_string(), code_span: state.previous_token.as_ref().map_or(CodeSpan::default(), |t| t.code_span.clone()) })
     }
 }
 
 fn parse_struct_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError> {
-    if let Some(Token { token_type: TokenType::StructDeclaration(struct_declaration_data), .. }) = advance(state) {
-        let code_span = state.previous_token.as_ref().map(|t| t.code_span.clone()).unwrap_or(CodeSpan::default());
+    if let Some(Token { token_type: TokenType::StructDeclaration(struct_declaration_data), code_span }) = advance(state) {
         let mut struct_fields = struct_declaration_data.fields.into_iter();
 
         let struct_name = struct_declaration_data.name;
         let mut fields = Vec::new();
 
         while let Some(field) = struct_fields.next() {
-            fields.push(ASTNode::StructDeclarationField { name: field.name, data_type: field.data_type })
+            fields.push(ASTNode::StructDeclarationField { name: field.name, data_type: field.data_type, type_span: field.type_span, code_span: field.code_span })
         }
 
         Ok(ASTNode::StructDeclaration { name: struct_name, fields, code_span, scope: NO_SCOPE })
@@ -292,6 +481,7 @@ fn parse_struct_instantiation_token(token: &Token) -> Result<ASTNode, CodeError>
                     TokenType::Integer(value) => ASTNode::NumberLiteral { value: value.clone(), data_type: NailDataTypeDescriptor::Int, code_span: field.value.code_span.clone(), scope: NO_SCOPE },
                     TokenType::Float(value) => ASTNode::NumberLiteral { value: value.clone(), data_type: NailDataTypeDescriptor::Float, code_span: field.value.code_span.clone(), scope: NO_SCOPE },
                     TokenType::StringLiteral(value) => ASTNode::StringLiteral { value: value.clone(), code_span: field.value.code_span.clone(), scope: NO_SCOPE },
+                    TokenType::InterpolatedString(part_tokens) => parse_string_interpolation(part_tokens.clone(), field.value.code_span.clone())?,
                     _ => {
                         return Err(CodeError { message: format!("Unexpected token in struct field: {:?}", field.value.token_type), code_span: field.value.code_span.clone() });
                     }
@@ -327,6 +517,7 @@ fn parse_array_literal(state: &mut ParserState) -> Result<ASTNode, CodeError> {
                 TokenType::Float(value) => elements.push(ASTNode::NumberLiteral { value, data_type: NailDataTypeDescriptor::Float, code_span: token.code_span.clone(), scope: NO_SCOPE }),
                 TokenType::StringLiteral(value) => elements.push(ASTNode::StringLiteral { value, code_span: token.code_span.clone(), scope: NO_SCOPE }),
                 TokenType::Identifier(name) => elements.push(ASTNode::Identifier { name, code_span: token.code_span.clone(), scope: NO_SCOPE }),
+                TokenType::InterpolatedString(part_tokens) => elements.push(parse_string_interpolation(part_tokens, token.code_span.clone())?),
 
                 _ => return Err(CodeError { message: format!("Unexpected token in array: {:?}", token.token_type), code_span: token.code_span.clone() }),
             }
@@ -366,18 +557,20 @@ fn parse_enum_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError>
 }
 
 fn parse_function_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError> {
-    if let Some(Token { token_type: TokenType::FunctionSignature(tokens), .. }) = advance(state) {
-        let code_span = state.previous_token.as_ref().map(|t| t.code_span.clone()).unwrap_or(CodeSpan::default());
+    if let Some(Token { token_type: TokenType::FunctionSignature(tokens), code_span }) = advance(state) {
         let mut func_tokens = tokens.into_iter();
 
         // Parse function name
-        let name = if let Some(Token { token_type: TokenType::FunctionName(name), .. }) = func_tokens.next() {
-            name
+        let (name, name_span) = if let Some(Token { token_type: TokenType::FunctionName(name), code_span }) = func_tokens.next() {
+            (name, code_span)
         } else {
             return Err(CodeError { message: "Expected function name".to_string(), code_span: state.previous_token.as_ref().map_or(CodeSpan::default(), |t| t.code_span.clone()) });
         };
 
         let mut params = Vec::new();
+        let mut param_type_spans = Vec::new();
+        #[allow(unused_assignments)]
+        let mut type_span = CodeSpan::default();
         #[allow(unused_assignments)]
         let mut data_type = NailDataTypeDescriptor::Void;
 
@@ -385,14 +578,16 @@ fn parse_function_declaration(state: &mut ParserState) -> Result<ASTNode, CodeEr
         loop {
             match func_tokens.next() {
                 Some(Token { token_type: TokenType::Identifier(param_name), .. }) => {
-                    if let Some(Token { token_type: TokenType::TypeDeclaration(type_desc), .. }) = func_tokens.next() {
+                    if let Some(Token { token_type: TokenType::TypeDeclaration(type_desc), code_span: param_type_span }) = func_tokens.next() {
                         params.push((param_name.clone(), type_desc.clone()));
+                        param_type_spans.push(param_type_span);
 
                         // Check for comma or end of parameters
                         match func_tokens.next() {
                             Some(Token { token_type: TokenType::Comma, .. }) => continue,
-                            Some(Token { token_type: TokenType::FunctionReturnTypeDeclaration(rt), .. }) => {
+                            Some(Token { token_type: TokenType::FunctionReturnTypeDeclaration(rt), code_span: return_type_span }) => {
                                 data_type = rt;
+                                type_span = return_type_span;
                                 break;
                             }
                             Some(other) => return Err(CodeError { message: format!("Expected comma or return type declaration, found {:?}", other.token_type), code_span: other.code_span.clone() }),
@@ -410,8 +605,9 @@ fn parse_function_declaration(state: &mut ParserState) -> Result<ASTNode, CodeEr
                         });
                     }
                 }
-                Some(Token { token_type: TokenType::FunctionReturnTypeDeclaration(rt), .. }) => {
+                Some(Token { token_type: TokenType::FunctionReturnTypeDeclaration(rt), code_span: return_type_span }) => {
                     data_type = rt;
+                    type_span = return_type_span;
                     break;
                 }
                 Some(other) => return Err(CodeError { message: format!("Unexpected token in function declaration: {:?}", other.token_type), code_span: other.code_span.clone() }),
@@ -427,7 +623,7 @@ fn parse_function_declaration(state: &mut ParserState) -> Result<ASTNode, CodeEr
         // Parse function body
         let body = Box::new(parse_block(state)?);
 
-        Ok(ASTNode::FunctionDeclaration { name, params, data_type, body, code_span, scope: NO_SCOPE })
+        Ok(ASTNode::FunctionDeclaration { name, name_span, params, param_type_spans, data_type, type_span, body, code_span, scope: NO_SCOPE })
     } else {
         Err(CodeError { message: "Expected function declaration".to_string(), code_span: state.previous_token.as_ref().map_or(CodeSpan::default(), |t| t.code_span.clone()) })
     }
@@ -437,24 +633,24 @@ fn parse_const_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError
     let _ = expect_token(state, TokenType::ConstDeclaration)?;
     let _ = state.previous_token.as_ref().map(|t| t.code_span.clone()).unwrap_or(CodeSpan::default());
     let name = expect_identifier(state)?;
-    let data_type = parse_type_declaration(state)?;
+    let (data_type, type_span) = parse_type_declaration(state)?;
     let _ = expect_token(state, TokenType::Assignment)?;
     let value = Box::new(parse_expression(state, 0)?);
-    let code_span = expect_token(state, TokenType::EndStatementOrExpression)?;
+    let code_span = expect_end_of_statement(state)?;
 
-    Ok(ASTNode::ConstDeclaration { name, data_type, value, code_span, scope: NO_SCOPE })
+    Ok(ASTNode::ConstDeclaration { name, data_type, type_span, value, code_span, scope: NO_SCOPE })
 }
 
 fn parse_variable_declaration(state: &mut ParserState) -> Result<ASTNode, CodeError> {
     let _ = expect_token(state, TokenType::VariableDeclaration)?;
     let _ = state.previous_token.as_ref().map(|t| t.code_span.clone()).unwrap_or(CodeSpan::default());
     let name = expect_identifier(state)?;
-    let data_type = parse_type_declaration(state)?;
+    let (data_type, type_span) = parse_type_declaration(state)?;
     let _ = expect_token(state, TokenType::Assignment)?;
     let value = Box::new(parse_expression(state, 0)?);
-    let code_span = expect_token(state, TokenType::EndStatementOrExpression)?;
+    let code_span = expect_end_of_statement(state)?;
 
-    Ok(ASTNode::VariableDeclaration { name, data_type, value, code_span, scope: NO_SCOPE })
+    Ok(ASTNode::VariableDeclaration { name, data_type, type_span, value, code_span, scope: NO_SCOPE })
 }
 
 fn parse_if_statement(state: &mut ParserState) -> Result<ASTNode, CodeError> {
@@ -518,7 +714,7 @@ fn parse_if_statement(state: &mut ParserState) -> Result<ASTNode, CodeError> {
     }
 
     let _ = expect_token(state, TokenType::BlockClose)?;
-    let code_span = expect_token(state, TokenType::EndStatementOrExpression)?;
+    let code_span = expect_end_of_statement(state)?;
 
     Ok(ASTNode::IfStatement { condition_branches, else_branch, code_span, scope: NO_SCOPE })
 }
@@ -536,13 +732,13 @@ fn parse_block(state: &mut ParserState) -> Result<ASTNode, CodeError> {
 fn parse_return_statement(state: &mut ParserState) -> Result<ASTNode, CodeError> {
     let _ = expect_token(state, TokenType::Return)?;
     let statement = parse_statement(state)?;
-    let code_span = expect_token(state, TokenType::EndStatementOrExpression)?;
+    let code_span = expect_end_of_statement(state)?;
     Ok(ASTNode::ReturnDeclaration { statement: Box::new(statement.clone()), code_span: code_span.clone(), scope: NO_SCOPE })
 }
 
-fn parse_type_declaration(state: &mut ParserState) -> Result<NailDataTypeDescriptor, CodeError> {
-    if let Some(Token { token_type: TokenType::TypeDeclaration(data_type), .. }) = advance(state) {
-        Ok(data_type)
+fn parse_type_declaration(state: &mut ParserState) -> Result<(NailDataTypeDescriptor, CodeSpan), CodeError> {
+    if let Some(Token { token_type: TokenType::TypeDeclaration(data_type), code_span }) = advance(state) {
+        Ok((data_type, code_span))
     } else {
         let error = CodeError {
             message: format!("Expected type declaration, found {:?}", state.tokens.peek().map(|token| token.token_type.clone()).unwrap_or(TokenType::EndOfFile)),
@@ -1038,4 +1234,68 @@ mod tests {
         "#;
         assert_eq!(remove_whitespace(&format!("{:#?}", result)), remove_whitespace(expected));
     }
+
+    // Renders an expression tree in prefix form, so tests can check its shape without comparing spans
+    fn expression_shape(node: &ASTNode) -> String {
+        match node {
+            ASTNode::Program { statements, .. } => statements.iter().map(expression_shape).collect::<Vec<_>>().join(" "),
+            ASTNode::ConstDeclaration { value, .. } => expression_shape(value),
+            ASTNode::BinaryOperation { left, operator, right, .. } => format!("({} {} {})", operator, expression_shape(left), expression_shape(right)),
+            ASTNode::Identifier { name, .. } => name.clone(),
+            ASTNode::NumberLiteral { value, .. } => value.clone(),
+            other => format!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modulo_binds_tighter_than_addition_and_comparison() {
+        let result = parse(lexer("c x:b = turn + 1 % 10 == zero;")).unwrap();
+        assert_eq!(expression_shape(&result), "(== (+ turn (% 1 10)) zero)");
+    }
+
+    #[test]
+    fn test_modulo_shares_a_level_with_multiplication() {
+        assert_eq!(expression_shape(&parse(lexer("c x:i = one % two * three;")).unwrap()), "(* (% one two) three)");
+        assert_eq!(expression_shape(&parse(lexer("c x:i = one * two % three;")).unwrap()), "(% (* one two) three)");
+        assert_eq!(expression_shape(&parse(lexer("c x:i = one - two % 10;")).unwrap()), "(- one (% two 10))");
+    }
+
+    #[test]
+    fn test_empty_input_parses_to_an_empty_program() {
+        for input in ["", "   \n\t\n  ", "// just a comment", "// one comment\n\n// and another\n"] {
+            match parse(lexer(input)) {
+                Ok(ASTNode::Program { statements, .. }) => assert!(statements.is_empty(), "{:?} should have no statements", input),
+                other => panic!("{:?} should parse to an empty program, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_semicolon_is_reported_after_the_statement() {
+        let error = parse(lexer("c first:i = 1\nc second:i = 2;")).unwrap_err();
+        assert_eq!(error.message, "Expected ';' after statement");
+        assert_eq!(error.code_span, CodeSpan { start_line: 1, start_column: 14, end_line: 1, end_column: 15 });
+    }
+
+    fn declared_value(input: &str) -> ASTNode {
+        match parse(lexer(input)) {
+            Ok(ASTNode::Program { statements, .. }) => match statements.last() {
+                Some(ASTNode::ConstDeclaration { value, .. }) => (**value).clone(),
+                other => panic!("{:?} should end with a const declaration, got {:?}", input, other),
+            },
+            other => panic!("{:?} should parse, got {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn test_string_interpolation_inside_other_expressions() {
+        let ASTNode::ArrayLiteral { elements, .. } = declared_value("c names:a:s = [`a {first}`, `b`];") else { panic!("expected an array") };
+        assert!(matches!(&elements[0], ASTNode::StringInterpolation { parts, .. } if parts.len() == 2));
+
+        let ASTNode::StructInstantiation { fields, .. } = declared_value("struct Point { label:s } c spot:struct:Point = Point { label: `at {place}` };") else { panic!("expected a struct") };
+        assert!(matches!(&fields[0], ASTNode::StructInstantiationField { value, .. } if matches!(**value, ASTNode::StringInterpolation { .. })));
+
+        let ASTNode::StringInterpolation { parts, .. } = declared_value("c shout:s = `{upper(word)}!`;") else { panic!("expected an interpolated string") };
+        assert!(matches!(&parts[0], ASTNode::FunctionCall { name, args, .. } if name == "upper" && args.len() == 1));
+    }
 }
@@ -1,28 +1,37 @@
+use crate::lexer::CodeSpan;
 use crate::lexer::NailDataTypeDescriptor;
 use crate::parser::ASTNode;
 
 use crate::lexer::Operation;
+use crate::utils::create_transpilation_cargo_toml;
 
 use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
 
 pub struct Transpiler {
     indent_level: usize,
     scope_level: usize,
+    statement_lines: Vec<(usize, CodeSpan)>, // the generated Rust line each Nail statement starts on, in output order
 }
 
 impl Transpiler {
     pub fn new() -> Self {
-        Transpiler { indent_level: 0, scope_level: 0 }
+        Transpiler { indent_level: 0, scope_level: 0, statement_lines: Vec::new() }
     }
 
     pub fn transpile(&mut self, node: &ASTNode) -> Result<String, std::fmt::Error> {
+        self.statement_lines.clear();
         let mut output = String::new();
         writeln!(output, "use tokio;")?;
         writeln!(output)?;
         writeln!(output, "#[tokio::main]")?;
         writeln!(output, "async fn main() {{")?;
         self.indent_level += 1;
-        self.transpile_node(node, &mut output)?;
+        let mut node = node.clone();
+        fold_constants(&mut node);
+        self.transpile_node(&node, &mut output)?;
         self.indent_level -= 1;
         writeln!(output, "}}")?;
         let output = insert_semicolons(output);
@@ -35,6 +44,7 @@ impl Transpiler {
             ASTNode::StructInstantiationField { .. } => todo!(),
             ASTNode::Program { statements, .. } => {
                 for stmt in statements {
+                    self.record_statement_line(stmt, output);
                     self.transpile_node(stmt, output)?;
                     writeln!(output)?;
                 }
@@ -98,6 +108,7 @@ impl Transpiler {
             }
             ASTNode::Block { statements, .. } => {
                 for stmt in statements {
+                    self.record_statement_line(stmt, output);
                     self.transpile_node(stmt, output)?;
                 }
             }
@@ -119,6 +130,23 @@ impl Transpiler {
             ASTNode::StringLiteral { value, .. } => {
                 write!(output, "\"{}\".to_string()", value.replace("\"", "\\\""))?;
             }
+            ASTNode::StringInterpolation { parts, .. } => {
+                write!(output, "format!(\"")?;
+                for part in parts {
+                    match part {
+                        ASTNode::StringLiteral { value, .. } => write!(output, "{}", value.replace("\"", "\\\"").replace('{', "{{").replace('}', "}}"))?,
+                        _ => write!(output, "{{}}")?,
+                    }
+                }
+                write!(output, "\"")?;
+                for part in parts.iter().filter(|part| !matches!(part, ASTNode::StringLiteral { .. })) {
+                    // Function calls are written out as statements, indented and on their own line
+                    let mut argument = String::new();
+                    self.transpile_node(part, &mut argument)?;
+                    write!(output, ", {}", argument.trim())?;
+                }
+                write!(output, ")")?;
+            }
 
             ASTNode::ReturnDeclaration { statement, .. } => {
                 write!(output, "{}return ", self.indent())?;
@@ -223,6 +251,7 @@ impl Transpiler {
             Operation::Sub => "-",
             Operation::Mul => "*",
             Operation::Div => "/",
+            Operation::Mod => "%",
             Operation::Eq => "==",
             Operation::Ne => "!=",
             Operation::Lt => "<",
@@ -239,6 +268,97 @@ impl Transpiler {
     fn indent(&self) -> String {
         "    ".repeat(self.indent_level)
     }
+
+    fn record_statement_line(&mut self, statement: &ASTNode, output: &str) {
+        if statement.code_span().start_line > 0 {
+            self.statement_lines.push((output.matches('\n').count() + 1, statement.code_span().clone()));
+        }
+    }
+
+    // The span of the Nail statement a line of the last transpiled output came from, for pointing rustc errors back at the source.
+    // Lines before the first statement, like the generated main header, have none.
+    pub fn nail_span(&self, rust_line: usize) -> Option<CodeSpan> {
+        self.statement_lines.iter().rev().find(|(line, _)| *line <= rust_line).map(|(_, code_span)| code_span.clone())
+    }
+}
+
+// Replaces arithmetic on two number literals of the same type with its result, innermost first, so `2 + 3 * 4` becomes `14`.
+// Anything that would overflow, divide by zero or not be finite is left alone for rustc to report.
+fn fold_constants(node: &mut ASTNode) {
+    match node {
+        ASTNode::Program { statements, .. } | ASTNode::Block { statements, .. } => statements.iter_mut().for_each(fold_constants),
+        ASTNode::FunctionDeclaration { body, .. } | ASTNode::LambdaDeclaration { body, .. } => fold_constants(body),
+        ASTNode::FunctionCall { args, .. } => args.iter_mut().for_each(fold_constants),
+        ASTNode::VariableDeclaration { value, .. } | ASTNode::ConstDeclaration { value, .. } | ASTNode::StructInstantiationField { value, .. } => fold_constants(value),
+        ASTNode::IfStatement { condition_branches, else_branch, .. } => {
+            for (condition, branch) in condition_branches {
+                fold_constants(condition);
+                fold_constants(branch);
+            }
+            if let Some(branch) = else_branch {
+                fold_constants(branch);
+            }
+        }
+        ASTNode::StructInstantiation { fields, .. } => fields.iter_mut().for_each(fold_constants),
+        ASTNode::ArrayLiteral { elements, .. } | ASTNode::StringInterpolation { parts: elements, .. } => elements.iter_mut().for_each(fold_constants),
+        ASTNode::UnaryOperation { operand, .. } => fold_constants(operand),
+        ASTNode::ReturnDeclaration { statement, .. } => fold_constants(statement),
+        ASTNode::BinaryOperation { left, operator, right, code_span, scope } => {
+            fold_constants(left);
+            fold_constants(right);
+            if let Some((value, data_type)) = fold_number_literals(left, operator, right) {
+                *node = ASTNode::NumberLiteral { value, data_type, code_span: code_span.clone(), scope: *scope };
+            }
+        }
+        _ => {}
+    }
+}
+
+fn fold_number_literals(left: &ASTNode, operator: &Operation, right: &ASTNode) -> Option<(String, NailDataTypeDescriptor)> {
+    let (ASTNode::NumberLiteral { value: left, data_type: left_type, .. }, ASTNode::NumberLiteral { value: right, data_type: right_type, .. }) = (left, right) else {
+        return None;
+    };
+    if left_type != right_type {
+        return None;
+    }
+
+    match left_type {
+        NailDataTypeDescriptor::Int => {
+            let (left, right): (i64, i64) = (left.parse().ok()?, right.parse().ok()?);
+            let result = match operator {
+                Operation::Add => left.checked_add(right),
+                Operation::Sub => left.checked_sub(right),
+                Operation::Mul => left.checked_mul(right),
+                Operation::Div => left.checked_div(right),
+                Operation::Mod => left.checked_rem(right),
+                _ => None,
+            }?;
+            Some((result.to_string(), NailDataTypeDescriptor::Int))
+        }
+        NailDataTypeDescriptor::Float => {
+            let (left, right): (f64, f64) = (left.parse().ok()?, right.parse().ok()?);
+            let result = match operator {
+                Operation::Add => left + right,
+                Operation::Sub => left - right,
+                Operation::Mul => left * right,
+                Operation::Div => left / right,
+                Operation::Mod => left % right,
+                _ => return None,
+            };
+            // Debug formatting keeps the decimal point, so the literal stays an f64 in the generated Rust
+            result.is_finite().then(|| (format!("{:?}", result), NailDataTypeDescriptor::Float))
+        }
+        _ => None,
+    }
+}
+
+// Writes the program out as a standalone Cargo project, the same Cargo.toml and src/main.rs the IDE builds from
+pub fn emit_project(node: &ASTNode, output_dir: &Path) -> io::Result<()> {
+    let rust_code = Transpiler::new().transpile(node).map_err(io::Error::other)?;
+    fs::create_dir_all(output_dir.join("src"))?;
+    fs::write(output_dir.join("Cargo.toml"), create_transpilation_cargo_toml())?;
+    fs::write(output_dir.join("src").join("main.rs"), rust_code)?;
+    Ok(())
 }
 
 fn insert_semicolons(code: String) -> String {
@@ -249,8 +369,8 @@ fn insert_semicolons(code: String) -> String {
         let trimmed = line.trim();
         let mut new_line = line.to_string();
 
-        // check if the line ends with an await, .to_string(), or a number with a white space or a \n after it and add a ; in that case or if ends with a number
-        if trimmed.ends_with("await") || trimmed.ends_with(".to_string()") || trimmed.chars().last().unwrap_or_default().is_ascii_digit() {
+        // check if the line ends with an await, .to_string(), a format!(...), or a number with a white space or a \n after it and add a ; in that case or if ends with a number
+        if trimmed.ends_with("await") || trimmed.ends_with(".to_string()") || (trimmed.contains("format!(") && trimmed.ends_with(')')) || trimmed.chars().last().unwrap_or_default().is_ascii_digit() {
             // or if it ends with a number ||
             let next_line = lines.get(i + 1).unwrap_or(&"");
             if next_line.trim().is_empty() || next_line.trim().starts_with("//") {
@@ -263,3 +383,55 @@ fn insert_semicolons(code: String) -> String {
 
     result.join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer;
+    use crate::parser::parse;
+
+    fn transpile_source(input: &str) -> String {
+        Transpiler::new().transpile(&parse(lexer(input)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_constant_folding_of_literal_arithmetic() {
+        assert!(transpile_source("c x:i = 2 + 3;").contains("let x: i64 = 5;"));
+        assert!(transpile_source("c x:i = 2 + 3 * 4 - 1;").contains("let x: i64 = 13;"));
+        assert!(transpile_source("c x:f = 1.5 * 2.0;").contains("let x: f64 = 3.0;"));
+        assert!(transpile_source("c x:i = 7 + 12 % 10;").contains("let x: i64 = 9;"));
+    }
+
+    #[test]
+    fn test_constant_folding_leaves_other_expressions_alone() {
+        assert!(transpile_source("c x:i = y + 3;").contains("let x: i64 = y + 3;"));
+        assert!(transpile_source("c x:i = 1 / 0;").contains("let x: i64 = 1 / 0;"));
+        assert!(transpile_source("c x:i = 9223372036854775807 + 1;").contains("let x: i64 = 9223372036854775807 + 1;"));
+        assert!(transpile_source("c x:b = 1 < 2;").contains("let x: bool = 1 < 2;"));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert!(transpile_source("c greeting:s = `Hello, {name}!`;").contains(r#"let greeting: String = format!("Hello, {}!", name);"#));
+        assert!(transpile_source("c total:s = `{count * 2} items`;").contains(r#"let total: String = format!("{} items", count * 2);"#));
+        assert!(transpile_source("c braces:s = `{{literal}} {name}`;").contains(r#"let braces: String = format!("{{literal}} {}", name);"#));
+        assert!(transpile_source("c shout:s = `{upper(word)}!`;").contains(r#"let shout: String = format!("{}!", upper(word).await);"#));
+        assert!(transpile_source("c plain:s = `no {{braces}} here`;").contains(r#"let plain: String = "no {braces} here".to_string();"#));
+    }
+
+    #[test]
+    fn test_emit_project() {
+        let output_dir = std::env::temp_dir().join(format!("nail_emit_project_{}", std::process::id()));
+        let source = "c total:i = 2 + 3;";
+        emit_project(&parse(lexer(source)).unwrap(), &output_dir).unwrap();
+
+        let cargo_toml = fs::read_to_string(output_dir.join("Cargo.toml"));
+        let main_rs = fs::read_to_string(output_dir.join("src").join("main.rs"));
+        fs::remove_dir_all(&output_dir).unwrap();
+
+        assert_eq!(cargo_toml.unwrap(), create_transpilation_cargo_toml());
+        let main_rs = main_rs.unwrap();
+        assert_eq!(main_rs, transpile_source(source));
+        assert!(main_rs.contains("let total: i64 = 5;"));
+    }
+}
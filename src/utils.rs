@@ -1,11 +1,13 @@
 use crate::checker::checker;
+use crate::commands::{execute_command, matching_commands, EditorCommand};
 use crate::parser::parse;
 use crate::parser::ASTNode;
 use crate::transpilier::Transpiler;
 use crate::CodeError;
 use crate::Editor;
 use log::error;
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseEventKind};
+use regex::Regex;
 use std::backtrace::Backtrace;
 use std::panic;
 use std::path::Path;
@@ -13,9 +15,10 @@ use std::sync::{
     mpsc::{Receiver, Sender},
     Arc, Mutex,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use std::process::Command;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
 
 use crate::lexer;
 
@@ -23,6 +26,7 @@ use ratatui::prelude::Alignment;
 
 use rayon::prelude::*;
 
+use ratatui::layout::Margin;
 use ratatui::prelude::Rect;
 use ratatui::widgets::Clear;
 use std::fs;
@@ -33,6 +37,7 @@ use std::sync::MutexGuard;
 use std::thread;
 
 use crate::colorizer::colorize_code;
+use crate::colorizer::ColorScheme;
 
 use ratatui::crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -43,7 +48,7 @@ use ratatui::text::Span;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::Line,
     widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Tabs},
     Frame, Terminal,
@@ -54,6 +59,15 @@ use std::io::Stdout;
 pub enum EditorMessage {
     Shutdown,
     BuildStart,
+    RunStart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DialogMode {
+    None,
+    Diagnostics,
+    BuildOutput,
+    CommandPalette,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,6 +76,7 @@ pub enum BuildStatus {
     Parsing,
     Transpiling,
     Compiling,
+    Running,
     Complete,
     Failed(String),
 }
@@ -123,8 +138,12 @@ pub fn draw_thread_logic(terminal_arc: Arc<Mutex<Terminal<CrosstermBackend<io::S
                 .highlight_style(Style::default().fg(editor.theme.operator));
             f.render_widget(tabs, chunks[0]);
 
-            // Create a horizontal layout for the main content area
-            let content_layout = Layout::default().direction(Direction::Horizontal).constraints([Constraint::Min(0), Constraint::Length(1)].as_ref()).split(chunks[1]);
+            // Create a horizontal layout for the main content area, the transpile preview takes the right half when it's open
+            let preview_width = if editor.show_transpile_preview { chunks[1].width / 2 } else { 0 };
+            let content_layout =
+                Layout::default().direction(Direction::Horizontal).constraints([Constraint::Min(0), Constraint::Length(1), Constraint::Length(preview_width)].as_ref()).split(chunks[1]);
+            editor.viewport_height = content_layout[0].height.saturating_sub(2) as usize; // Subtract 2 for the border
+            editor.viewport_width = content_layout[0].width.saturating_sub(2) as usize;
 
             // Render main content
             // let visible_content: Vec<Line> = colorize_code(&editor.content.join("\n"), &editor.theme)
@@ -134,18 +153,42 @@ pub fn draw_thread_logic(terminal_arc: Arc<Mutex<Terminal<CrosstermBackend<io::S
             //     .cloned()
             //     .collect();
 
-            let visible_content: Vec<Line> = editor
-                .content
+            let visible_lines = editor.visible_lines();
+            let visible_content: Vec<Line> = visible_lines
                 .iter()
-                .skip(editor.scroll_position as usize)
-                .take(chunks[1].height as usize - 2) // Subtract 2 for the border
-                .map(|line| Line::from(vec![Span::styled(line.clone(), Style::default().fg(editor.theme.default).bg(editor.theme.background))]))
+                .map(|&index| match editor.fold_ending_at(index) {
+                    Some(fold_end) => folded_line(&editor, index, fold_end),
+                    None => editor_line(&editor, index),
+                })
                 .collect();
 
-            let paragraph = Paragraph::new(visible_content).block(Block::default().borders(Borders::ALL).title("NAIL")).style(Style::default().bg(editor.theme.background).fg(editor.theme.default));
+            let paragraph = Paragraph::new(visible_content)
+                .block(Block::default().borders(Borders::ALL).title("NAIL"))
+                .style(Style::default().bg(editor.theme.background).fg(editor.theme.default))
+                .scroll((0, editor.horizontal_scroll));
 
             f.render_widget(paragraph, content_layout[0]);
 
+            display_error_underlines(f, &editor, &visible_lines, content_layout[0]);
+            display_rulers(f, &editor, visible_lines.len(), content_layout[0]);
+
+            // Bookmarks sit on the border, so they stay put while the text scrolls sideways
+            display_bookmarks(f, &editor, &visible_lines, content_layout[0]);
+
+            let longest_line = visible_lines.iter().map(|&index| editor.content[index].chars().count()).max().unwrap_or(0);
+            if longest_line > editor.viewport_width || editor.horizontal_scroll > 0 {
+                let horizontal_scrollbar = Scrollbar::default()
+                    .style(Style::default().fg(editor.theme.default))
+                    .orientation(ScrollbarOrientation::HorizontalBottom)
+                    .symbols(ratatui::symbols::scrollbar::HORIZONTAL)
+                    .begin_symbol(None)
+                    .end_symbol(None);
+
+                let mut horizontal_scrollbar_state = ScrollbarState::default().content_length(longest_line).viewport_content_length(editor.viewport_width).position(editor.horizontal_scroll as usize);
+
+                f.render_stateful_widget(horizontal_scrollbar, content_layout[0].inner(Margin { horizontal: 1, vertical: 0 }), &mut horizontal_scrollbar_state);
+            }
+
             let scrollbar = Scrollbar::default()
                 .style(Style::default().fg(editor.theme.default))
                 .orientation(ScrollbarOrientation::VerticalRight)
@@ -158,19 +201,32 @@ pub fn draw_thread_logic(terminal_arc: Arc<Mutex<Terminal<CrosstermBackend<io::S
             f.render_stateful_widget(scrollbar, content_layout[1], &mut scrollbar_state);
 
             // Set cursor
-            let cursor_y = editor.cursor_y.saturating_sub(editor.scroll_position.into());
-            if cursor_y < content_layout[0].height.saturating_sub(2) as usize {
-                f.set_cursor(content_layout[0].x + editor.cursor_x as u16 + 1, content_layout[0].y + cursor_y as u16 + 1);
+            if let Some(cursor_row) = visible_lines.iter().position(|&line| line == editor.cursor_y) {
+                let cursor_column = (editor.cursor_x as u16).saturating_sub(editor.horizontal_scroll);
+                f.set_cursor(content_layout[0].x + cursor_column + 1, content_layout[0].y + cursor_row as u16 + 1);
             }
 
+            display_status_message(f, &editor, chunks[2]);
+
             // Display building indicator
             if editor.build_status != BuildStatus::Idle {
                 display_build_status(f, &editor);
             }
 
-            // Check and draw errors
-            if let Some(error) = &editor.code_error {
-                display_error(f, error, &editor, content_layout[0]);
+            // Draw the first error whose line is on screen, skipping ones with no span or inside a fold
+            if let Some((error, row)) = editor.code_errors.iter().filter(|error| error.code_span.start_line > 0).find_map(|error| Some((error, editor.screen_row(error.code_span.start_line - 1)?))) {
+                display_error(f, error, row, &editor, content_layout[0]);
+            }
+
+            match editor.dialog_mode {
+                DialogMode::Diagnostics => display_diagnostics(f, &editor, content_layout[0]),
+                DialogMode::BuildOutput => display_build_output(f, &editor, content_layout[0]),
+                DialogMode::CommandPalette => display_command_palette(f, &editor, content_layout[0]),
+                DialogMode::None => {}
+            }
+
+            if editor.show_transpile_preview {
+                display_transpile_preview(f, &editor, content_layout[2]);
             }
         });
 
@@ -181,15 +237,25 @@ pub fn draw_thread_logic(terminal_arc: Arc<Mutex<Terminal<CrosstermBackend<io::S
     }
 }
 
+const SPINNER_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+
 fn display_build_status(f: &mut Frame, editor: &Editor) {
+    // While a build is in progress a spinner and the elapsed seconds show the IDE hasn't frozen
+    let elapsed = editor.build_started.map(|started| started.elapsed()).unwrap_or_default();
+    let in_progress = |stage: &str| format!("{} {} {}s", stage, SPINNER_FRAMES[(elapsed.as_millis() / 100) as usize % SPINNER_FRAMES.len()], elapsed.as_secs());
+
     let build_status = Line::from(vec![Span::styled(
         match &editor.build_status {
-            BuildStatus::Idle => "Not Started",
-            BuildStatus::Parsing => "Starting",
-            BuildStatus::Transpiling => "Transpiling",
-            BuildStatus::Compiling => "Compiling",
-            BuildStatus::Complete => "Complete",
-            BuildStatus::Failed(err) => err,
+            BuildStatus::Idle => "Not Started".to_string(),
+            BuildStatus::Parsing => in_progress("Starting"),
+            BuildStatus::Transpiling => in_progress("Transpiling"),
+            BuildStatus::Compiling => in_progress("Compiling"),
+            BuildStatus::Running => in_progress("Running"),
+            BuildStatus::Complete => match editor.build_time {
+                Some(build_time) => format!("Complete in {:.1}s", build_time.as_secs_f32()),
+                None => "Complete".to_string(),
+            },
+            BuildStatus::Failed(err) => err.clone(),
         },
         Style::default().fg(editor.theme.default),
     )]);
@@ -205,10 +271,123 @@ fn display_build_status(f: &mut Frame, editor: &Editor) {
     f.render_widget(paragraph, status_area);
 }
 
-fn display_error(f: &mut Frame, error: &CodeError, editor: &Editor, content_area: Rect) {
-    let error_line = error.code_span.start_line.saturating_sub(editor.scroll_position as usize);
-    let error_column = error.code_span.start_column;
-    let error_message = error.message.clone();
+fn editor_line(editor: &Editor, index: usize) -> Line<'static> {
+    let line = &editor.content[index];
+    let style = Style::default().fg(editor.theme.default).bg(editor.theme.background);
+    let mut spans = if editor.show_trailing_whitespace {
+        // Trailing whitespace gets its own span so it can be flagged with a background colour
+        let content_end = line.trim_end().len();
+        let (content, trailing_whitespace) = line.split_at(content_end);
+        vec![Span::styled(content.to_string(), style), Span::styled(trailing_whitespace.to_string(), Style::default().bg(editor.theme.warning_background))]
+    } else {
+        vec![Span::styled(line.clone(), style)]
+    };
+
+    if let Some(max_line_length) = editor.max_line_length {
+        spans = flag_overlong_text(editor, spans, max_line_length);
+    }
+    Line::from(spans)
+}
+
+// Splits the spans at max_line_length characters and gives everything after it the warning background
+fn flag_overlong_text(editor: &Editor, spans: Vec<Span<'static>>, max_line_length: usize) -> Vec<Span<'static>> {
+    let mut column = 0;
+    let mut flagged = Vec::new();
+    for span in spans {
+        let length = span.content.chars().count();
+        if column + length <= max_line_length {
+            column += length;
+            flagged.push(span);
+            continue;
+        }
+
+        let split_at = span.content.char_indices().nth(max_line_length.saturating_sub(column)).map_or(span.content.len(), |(index, _)| index);
+        let (within, beyond) = span.content.split_at(split_at);
+        if !within.is_empty() {
+            flagged.push(Span::styled(within.to_string(), span.style));
+        }
+        flagged.push(Span::styled(beyond.to_string(), span.style.bg(editor.theme.warning_background)));
+        column += length;
+    }
+    flagged
+}
+
+// A folded block shows its first line followed by `...` and the line holding the closing brace
+fn folded_line(editor: &Editor, start: usize, end: usize) -> Line<'static> {
+    let style = Style::default().fg(editor.theme.default).bg(editor.theme.background);
+    Line::from(vec![
+        Span::styled(editor.content[start].trim_end().to_string(), style),
+        Span::styled(" ... ".to_string(), Style::default().fg(editor.theme.comment).bg(editor.theme.background)),
+        Span::styled(editor.content[end].trim().to_string(), style),
+    ])
+}
+
+// Bookmarked lines get a marker drawn over the left border, so the text itself doesn't shift
+fn display_bookmarks(f: &mut Frame, editor: &Editor, visible_lines: &[usize], content_area: Rect) {
+    for (row, _) in visible_lines.iter().enumerate().filter(|(_, line)| editor.bookmarks.contains(line)) {
+        let marker = Paragraph::new("◆").style(Style::default().fg(editor.theme.bookmark).bg(editor.theme.background));
+        let marker_area = Rect::new(content_area.x, content_area.y + row as u16 + 1, 1, 1); // +1 for the border
+        f.render_widget(marker, marker_area);
+    }
+}
+
+// Underlines the exact characters each error's span covers, keeping the text's own colours
+fn display_error_underlines(f: &mut Frame, editor: &Editor, visible_lines: &[usize], content_area: Rect) {
+    let underline = Style::default().add_modifier(Modifier::UNDERLINED).underline_color(editor.theme.error);
+    let buffer = f.buffer_mut();
+    for span in editor.code_errors.iter().map(|error| &error.code_span).filter(|span| span.start_line > 0) {
+        for (row, &line) in visible_lines.iter().enumerate() {
+            // Code spans count lines and columns from 1, and end just after the last character
+            let line_number = line + 1;
+            if line_number < span.start_line || line_number > span.end_line.max(span.start_line) {
+                continue;
+            }
+            let start = if line_number == span.start_line { span.start_column.saturating_sub(1) } else { 0 };
+            let end = if line_number == span.end_line { span.end_column.saturating_sub(1) } else { editor.content[line].chars().count() };
+            for column in start..end.max(start + 1) {
+                let Some(offset) = column.checked_sub(editor.horizontal_scroll as usize).filter(|&offset| offset < editor.viewport_width) else {
+                    continue;
+                };
+                if let Some(cell) = buffer.cell_mut((content_area.x + offset as u16 + 1, content_area.y + row as u16 + 1)) {
+                    cell.set_style(underline);
+                }
+            }
+        }
+    }
+}
+
+// Rulers only fill empty cells, so text running past them stays readable
+fn display_rulers(f: &mut Frame, editor: &Editor, rows: usize, content_area: Rect) {
+    let buffer = f.buffer_mut();
+    for &column in &editor.rulers {
+        let Some(offset) = column.checked_sub(editor.horizontal_scroll as usize).filter(|&offset| offset < editor.viewport_width) else {
+            continue;
+        };
+        let x = content_area.x + offset as u16 + 1; // +1 for the border
+        for row in 0..rows as u16 {
+            if let Some(cell) = buffer.cell_mut((x, content_area.y + row + 1)) {
+                if cell.symbol() == " " {
+                    cell.set_symbol("│").set_fg(editor.theme.ruler);
+                }
+            }
+        }
+    }
+}
+
+// Flashed status messages stay on the bottom line for a couple of seconds
+fn display_status_message(f: &mut Frame, editor: &Editor, status_area: Rect) {
+    if let Some((message, shown_at)) = &editor.status_message {
+        if shown_at.elapsed() < Duration::from_secs(2) {
+            let paragraph = Paragraph::new(message.clone()).style(Style::default().fg(editor.theme.default).bg(editor.theme.background));
+            f.render_widget(paragraph, status_area);
+        }
+    }
+}
+
+// The message goes on the row under the error's line, which is drawn on `row`
+fn display_error(f: &mut Frame, error: &CodeError, row: usize, editor: &Editor, content_area: Rect) {
+    let error_column = (error.code_span.start_column as u16).saturating_sub(editor.horizontal_scroll);
+    let error_message = format!("^ {}", error.message);
 
     // Only display the error if it's within the visible area
 
@@ -217,8 +396,8 @@ fn display_error(f: &mut Frame, error: &CodeError, editor: &Editor, content_area
     let paragraph = Paragraph::new(error_message.clone()).style(Style::default().fg(editor.theme.error).bg(editor.theme.background)).alignment(Alignment::Left);
 
     let error_area = Rect::new(
-        content_area.x + error_column as u16,
-        content_area.y + error_line as u16 + 1, // +1 for the border
+        content_area.x + error_column,
+        content_area.y + row as u16 + 2, // +1 for the border, +1 to go under the line
         error_message.width() as u16,
         1,
     );
@@ -226,6 +405,177 @@ fn display_error(f: &mut Frame, error: &CodeError, editor: &Editor, content_area
     f.render_widget(paragraph, error_area);
 }
 
+fn display_diagnostics(f: &mut Frame, editor: &Editor, content_area: Rect) {
+    // The selected problem is expanded with the line it is on, the others are one line each
+    let mut diagnostics: Vec<Line> = Vec::new();
+    let mut selected_rows = 0..0;
+    if editor.code_errors.is_empty() {
+        diagnostics.push(Line::from(vec![Span::styled("No problems found", Style::default().fg(editor.theme.default))]));
+    }
+    for (index, error) in editor.code_errors.iter().enumerate() {
+        let style = if index == editor.diagnostics_index { Style::default().fg(editor.theme.background).bg(editor.theme.error) } else { Style::default().fg(editor.theme.error) };
+        let first_row = diagnostics.len();
+        let text = if error.code_span.start_line > 0 { format!("{}:{}  {}", error.code_span.start_line, error.code_span.start_column, error.message) } else { error.message.clone() };
+        diagnostics.push(Line::from(vec![Span::styled(text, style)]));
+        if index == editor.diagnostics_index {
+            diagnostics.extend(error_snippet(error, &editor.content, editor.theme));
+            selected_rows = first_row..diagnostics.len();
+        }
+    }
+
+    // The panel covers the bottom of the editor, at most half of it
+    let panel_height = (diagnostics.len() as u16 + 2).min(content_area.height / 2).max(3); // +2 for the border
+    let panel_area = Rect::new(content_area.x, content_area.y + content_area.height.saturating_sub(panel_height), content_area.width, panel_height);
+
+    // Keep the selected diagnostic in view when the list is longer than the panel
+    let visible_rows = panel_height.saturating_sub(2) as usize;
+    let list_scroll = selected_rows.end.saturating_sub(visible_rows).min(selected_rows.start) as u16;
+
+    let paragraph = Paragraph::new(diagnostics)
+        .scroll((list_scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title("DIAGNOSTICS"))
+        .style(Style::default().bg(editor.theme.background).fg(editor.theme.default));
+    f.render_widget(Clear, panel_area);
+    f.render_widget(paragraph, panel_area);
+}
+
+// The line an error is on with carets under its span, laid out like rustc:
+//    |
+// 12 | c total:i = count + 1;
+//    |             ^^^^^
+fn error_snippet(error: &CodeError, content: &[String], theme: &ColorScheme) -> Vec<Line<'static>> {
+    let span = &error.code_span;
+    // Errors with no span in the Nail source, like a rustc error in the generated main header, get no snippet
+    let Some(source_line) = span.start_line.checked_sub(1).and_then(|index| content.get(index)) else {
+        return Vec::new();
+    };
+
+    let line_number = span.start_line.to_string();
+    let gutter = format!("{} | ", " ".repeat(line_number.len()));
+    let gutter_style = Style::default().fg(theme.comment);
+    let line_length = source_line.chars().count();
+    let start = span.start_column.saturating_sub(1).min(line_length);
+    let end = if span.end_line > span.start_line { line_length } else { span.end_column.saturating_sub(1).min(line_length) };
+    let carets = "^".repeat(end.saturating_sub(start).max(1));
+
+    let mut snippet = vec![
+        Line::from(vec![Span::styled(gutter.trim_end().to_string(), gutter_style)]),
+        Line::from(vec![Span::styled(format!("{} | ", line_number), gutter_style), Span::styled(source_line.clone(), Style::default().fg(theme.default))]),
+        Line::from(vec![Span::styled(gutter.clone(), gutter_style), Span::styled(format!("{}{}", " ".repeat(start), carets), Style::default().fg(theme.error))]),
+    ];
+    if span.end_line > span.start_line {
+        snippet.push(Line::from(vec![Span::styled(format!("{}...continues to line {}", gutter, span.end_line), gutter_style)]));
+    }
+    snippet
+}
+
+fn display_build_output(f: &mut Frame, editor: &Editor, content_area: Rect) {
+    let output: Vec<Line> = if editor.build_output.is_empty() {
+        vec![Line::from(vec![Span::styled("No build output yet, press F7 to build", Style::default().fg(editor.theme.comment))])]
+    } else {
+        editor.build_output.iter().map(|line| Line::from(vec![Span::styled(line.clone(), Style::default().fg(editor.theme.default))])).collect()
+    };
+
+    // The pane always takes the bottom half of the editor
+    let pane_height = (content_area.height / 2).max(3);
+    let pane_area = Rect::new(content_area.x, content_area.y + content_area.height.saturating_sub(pane_height), content_area.width, pane_height);
+
+    let paragraph = Paragraph::new(output)
+        .scroll((editor.build_output_scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title("BUILD OUTPUT"))
+        .style(Style::default().bg(editor.theme.background).fg(editor.theme.default));
+    f.render_widget(Clear, pane_area);
+    f.render_widget(paragraph, pane_area);
+}
+
+// Read-only view of the Rust generated from the current code. When the code stops transpiling the
+// last good output stays up, dimmed, under a banner saying it's out of date.
+fn display_transpile_preview(f: &mut Frame, editor: &Editor, preview_area: Rect) {
+    let code_style = if editor.transpile_preview_stale { Style::default().fg(editor.theme.comment) } else { Style::default().fg(editor.theme.default) };
+    let mut lines: Vec<Line> = Vec::new();
+    if editor.transpile_preview_stale {
+        lines.push(Line::from(vec![Span::styled("Out of date: fix the errors to refresh", Style::default().fg(editor.theme.error))]));
+    }
+    lines.extend(editor.transpile_preview.iter().map(|line| Line::from(vec![Span::styled(line.clone(), code_style)])));
+
+    let paragraph = Paragraph::new(lines)
+        .scroll((editor.transpile_preview_scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title("RUST"))
+        .style(Style::default().bg(editor.theme.background).fg(editor.theme.default));
+    f.render_widget(paragraph, preview_area);
+}
+
+// The palette drops down from the top of the editor, listing matching commands with their keys
+fn display_command_palette(f: &mut Frame, editor: &Editor, content_area: Rect) {
+    let commands = matching_commands(&editor.command_palette_query);
+    let mut lines = vec![Line::from(vec![Span::styled(format!("> {}", editor.command_palette_query), Style::default().fg(editor.theme.default))])];
+    if commands.is_empty() {
+        lines.push(Line::from(vec![Span::styled("No matching commands", Style::default().fg(editor.theme.comment))]));
+    }
+
+    let palette_width = content_area.width.min(60);
+    let name_width = (palette_width as usize).saturating_sub(14); // leaves room for the keybinding and the border
+    for (index, info) in commands.iter().enumerate() {
+        let style = if index == editor.command_palette_index { Style::default().fg(editor.theme.background).bg(editor.theme.operator) } else { Style::default().fg(editor.theme.default) };
+        let keybinding = editor.keybinding_for(info.command).map(|chord| chord.to_string()).unwrap_or_default();
+        lines.push(Line::from(vec![Span::styled(format!("{:<width$}{:>10}", info.name, keybinding, width = name_width), style)]));
+    }
+
+    let palette_height = (lines.len() as u16 + 2).min(content_area.height); // +2 for the border
+    let palette_area = Rect::new(content_area.x + content_area.width.saturating_sub(palette_width) / 2, content_area.y + 1, palette_width, palette_height);
+
+    // Keep the selected command in view, the query line always stays at the top
+    let list_scroll = editor.command_palette_index.saturating_sub(palette_height.saturating_sub(4) as usize) as u16;
+
+    let paragraph =
+        Paragraph::new(lines).scroll((list_scroll, 0)).block(Block::default().borders(Borders::ALL).title("COMMANDS")).style(Style::default().bg(editor.theme.background).fg(editor.theme.default));
+    f.render_widget(Clear, palette_area);
+    f.render_widget(paragraph, palette_area);
+}
+
+// While a dialog is open it takes all key input except the shutdown keys. The chord that opened it,
+// as remapped in the config, closes it again like Esc does.
+fn handle_dialog_key(editor: &mut Editor, key_code: KeyCode, modifiers: KeyModifiers, tx_build: &Sender<EditorMessage>) {
+    let command = editor.command_for_key(key_code, modifiers);
+    match editor.dialog_mode {
+        DialogMode::Diagnostics => match key_code {
+            _ if command == Some(EditorCommand::ToggleDiagnostics) => editor.toggle_diagnostics(),
+            KeyCode::Up => editor.select_previous_diagnostic(),
+            KeyCode::Down => editor.select_next_diagnostic(),
+            KeyCode::Enter => editor.open_selected_diagnostic(),
+            KeyCode::Esc => editor.toggle_diagnostics(),
+            _ => {}
+        },
+        DialogMode::BuildOutput => match key_code {
+            _ if command == Some(EditorCommand::ToggleBuildOutput) => editor.toggle_build_output(),
+            KeyCode::Up => editor.scroll_build_output_up(1),
+            KeyCode::Down => editor.scroll_build_output_down(1),
+            KeyCode::PageUp => editor.scroll_build_output_up(10),
+            KeyCode::PageDown => editor.scroll_build_output_down(10),
+            KeyCode::Esc => editor.toggle_build_output(),
+            _ => {}
+        },
+        DialogMode::CommandPalette => match key_code {
+            _ if command == Some(EditorCommand::CommandPalette) => editor.dialog_mode = DialogMode::None,
+            KeyCode::Up => editor.select_previous_command(),
+            KeyCode::Down => editor.select_next_command(),
+            KeyCode::Enter => {
+                let selected = matching_commands(&editor.command_palette_query).get(editor.command_palette_index).map(|info| info.command);
+                editor.dialog_mode = DialogMode::None;
+                if let Some(command) = selected {
+                    execute_command(editor, command, tx_build);
+                }
+            }
+            KeyCode::Esc => editor.dialog_mode = DialogMode::None,
+            key_code => editor.edit_command_palette_query(key_code, modifiers),
+        },
+        DialogMode::None => {}
+    }
+}
+
+// How many lines one notch of the mouse wheel scrolls
+const WHEEL_SCROLL_LINES: usize = 3;
+
 pub fn key_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMessage>, tx: Sender<EditorMessage>, tx_build: Sender<EditorMessage>) {
     loop {
         // Check for messages
@@ -239,34 +589,43 @@ pub fn key_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMessa
 
         // Check for key input
         if event::poll(Duration::from_millis(100)).unwrap() {
-            if let Event::Key(key) = event::read().unwrap() {
-                let mut editor = lock(&editor_arc);
-                match key.code {
-                    KeyCode::Esc | KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // SEND SHUTDOWN SIGNAL
-                        let _ = tx.send(EditorMessage::Shutdown);
-                        break;
-                    }
-                    KeyCode::F(6) => editor.toggle_theme(),
-                    KeyCode::F(7) => {
-                        if editor.build_status == BuildStatus::Idle {
-                            let _ = tx_build.send(EditorMessage::BuildStart);
+            match event::read().unwrap() {
+                Event::Key(key) => {
+                    let mut editor = lock(&editor_arc);
+                    let bound_command = editor.command_for_key(key.code, key.modifiers);
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            // SEND SHUTDOWN SIGNAL
+                            let _ = tx.send(EditorMessage::Shutdown);
+                            break;
                         }
+                        _ if editor.dialog_mode != DialogMode::None => handle_dialog_key(&mut editor, key.code, key.modifiers, &tx_build),
+                        _ if bound_command.is_some() => execute_command(&mut editor, bound_command.expect("checked by the guard"), &tx_build),
+                        KeyCode::Char(c) => editor.insert_char(c),
+                        KeyCode::Up => editor.move_cursor_up(),
+                        KeyCode::Down => editor.move_cursor_down(),
+                        KeyCode::PageDown => editor.page_down(),
+                        KeyCode::PageUp => editor.page_up(),
+                        KeyCode::Tab => editor.next_tab(),
+                        KeyCode::BackTab => editor.previous_tab(),
+                        KeyCode::Backspace => editor.delete_char(),
+                        KeyCode::Enter => editor.insert_newline(),
+                        KeyCode::Left => editor.move_cursor_left(),
+                        KeyCode::Right => editor.move_cursor_right(),
+
+                        _ => {}
+                    }
+                    editor.scroll_horizontally_to_cursor();
+                    if bound_command != Some(EditorCommand::CenterCursor) {
+                        editor.recenter_count = 0;
                     }
-                    KeyCode::Char(c) => editor.insert_char(c),
-                    KeyCode::Up => editor.move_cursor_up(),
-                    KeyCode::Down => editor.move_cursor_down(),
-                    KeyCode::PageDown => editor.scroll_down(),
-                    KeyCode::PageUp => editor.scroll_up(),
-                    KeyCode::Tab => editor.next_tab(),
-                    KeyCode::BackTab => editor.previous_tab(),
-                    KeyCode::Backspace => editor.delete_char(),
-                    KeyCode::Enter => editor.insert_newline(),
-                    KeyCode::Left => editor.move_cursor_left(),
-                    KeyCode::Right => editor.move_cursor_right(),
-
-                    _ => {}
                 }
+                Event::Mouse(mouse) => match mouse.kind {
+                    MouseEventKind::ScrollUp => lock(&editor_arc).scroll_view_up(WHEEL_SCROLL_LINES),
+                    MouseEventKind::ScrollDown => lock(&editor_arc).scroll_view_down(WHEEL_SCROLL_LINES),
+                    _ => {}
+                },
+                _ => {}
             }
         }
     }
@@ -296,12 +655,17 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
             }
         };
 
-        if recv_result == EditorMessage::BuildStart {
-            log::info!("Received build signal");
+        if recv_result == EditorMessage::BuildStart || recv_result == EditorMessage::RunStart {
+            log::info!("Received {:?} signal", recv_result);
 
             // Step 1: Parse the content
             let mut editor = editor_arc.lock().unwrap();
             editor.build_status = BuildStatus::Parsing;
+            editor.build_started = Some(Instant::now());
+            editor.build_time = None;
+            editor.set_compile_errors(Vec::new());
+            editor.build_output.clear();
+            editor.build_output_scroll = 0;
             let tokens = lexer::lexer(&editor.content.join("\n"));
             drop(editor);
 
@@ -313,6 +677,7 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
                 Err(e) => {
                     let mut editor = editor_arc.lock().unwrap();
                     editor.build_status = BuildStatus::Failed(e.message.clone());
+                    editor.set_code_errors(vec![e.clone()]);
                     log::error!("Parsing failed: {:?}", e);
                     continue;
                 }
@@ -326,6 +691,7 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
                 Err(errors) => {
                     let mut editor = editor_arc.lock().unwrap();
                     editor.build_status = BuildStatus::Failed(errors[0].message.clone());
+                    editor.set_code_errors(errors.clone());
                     log::error!("Checker failed: {:?}", errors);
                     continue;
                 }
@@ -349,10 +715,9 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
                 }
             };
 
-            // Step 3: Write Rust code to a temporary file
+            // Step 3: Write Rust code into the transpilation project. The project and its target/ directory
+            // are kept between builds so cargo can reuse the compiled dependencies.
             let transpilation_dir = Path::new("./transpilation");
-            let _ = fs::remove_dir_all(transpilation_dir);
-            let _ = fs::create_dir(transpilation_dir);
 
             let transpilation_src_dir = transpilation_dir.join("src");
             if let Err(e) = fs::create_dir_all(&transpilation_src_dir) {
@@ -364,7 +729,7 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
 
             let transpilation_toml = crate::utils::create_transpilation_cargo_toml();
             let transpilation_toml_path = transpilation_dir.join("Cargo.toml");
-            if let Err(e) = fs::write(&transpilation_toml_path, &transpilation_toml) {
+            if let Err(e) = write_if_changed(&transpilation_toml_path, &transpilation_toml) {
                 let mut editor = editor_arc.lock().unwrap();
                 editor.build_status = BuildStatus::Failed(format!("Failed to write Cargo.toml file: {}", e));
                 log::error!("Failed to write Cargo.toml file: {}", e);
@@ -372,7 +737,7 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
             }
 
             let temp_file_path = transpilation_src_dir.join("main.rs");
-            if let Err(e) = fs::write(&temp_file_path, &rust_code) {
+            if let Err(e) = write_if_changed(&temp_file_path, &rust_code) {
                 let mut editor = editor_arc.lock().unwrap();
                 editor.build_status = BuildStatus::Failed(format!("Failed to write Rust code to file: {}", e));
                 log::error!("Failed to write Rust code to file: {}", e);
@@ -381,38 +746,58 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
             }
 
             // Step 4: Compile the Rust code
+            if recv_result == EditorMessage::RunStart {
+                run_transpiled_program(&editor_arc, transpilation_dir, &transpiler);
+                thread::sleep(std::time::Duration::from_millis(1000));
+                let mut editor = editor_arc.lock().unwrap();
+                editor.build_status = BuildStatus::Idle;
+                continue;
+            }
+
             let mut editor = editor_arc.lock().unwrap();
             editor.build_status = BuildStatus::Compiling;
             drop(editor); // Release the lock
             let output = Command::new("cargo")
                 .arg("run")
                 .arg("--release")
+                .arg("--message-format=short")
                 // run rustfmt or something
                 .current_dir(transpilation_dir)
                 .output();
 
             match output {
                 Ok(output) => {
+                    {
+                        let mut editor = editor_arc.lock().unwrap();
+                        editor.build_output = String::from_utf8_lossy(&output.stderr).lines().chain(String::from_utf8_lossy(&output.stdout).lines()).map(String::from).collect();
+                    }
+
                     if output.status.success() {
                         log::debug!("Compiler stdout: {}", String::from_utf8_lossy(&output.stdout));
 
                         let binary_path = transpilation_dir.join("target/release/nail_transpilation");
                         let destination_path = Path::new("./build");
-                        if let Err(e) = fs::rename(&binary_path, &destination_path) {
-                            log::error!("Failed to move binary: {}", e);
+                        if let Err(e) = fs::copy(&binary_path, destination_path) {
+                            log::error!("Failed to copy binary: {}", e);
                             let mut editor = editor_arc.lock().unwrap();
-                            editor.build_status = BuildStatus::Failed(format!("Failed to move binary: {}", e));
+                            editor.build_status = BuildStatus::Failed(format!("Failed to copy binary: {}", e));
                         } else {
                             let mut editor = editor_arc.lock().unwrap();
                             editor.build_status = BuildStatus::Complete;
-                        }
-                        if let Err(e) = fs::remove_dir_all(transpilation_dir) {
-                            log::error!("Failed to remove transpilation directory: {}", e);
+                            editor.build_time = editor.build_started.map(|started| started.elapsed());
                         }
                     } else {
-                        log::error!("Compiler stderr: {}", String::from_utf8_lossy(&output.stderr));
+                        let stderr = String::from_utf8_lossy(&output.stderr);
+                        log::error!("Compiler stderr: {}", stderr);
+                        let compile_errors = summarize_compiler_errors(&stderr, &transpiler);
+                        let summary = match compile_errors.len() {
+                            0 => stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("unknown error").trim().to_string(),
+                            1 => compile_errors[0].message.clone(),
+                            count => format!("{} (+{} more)", compile_errors[0].message, count - 1),
+                        };
                         let mut editor = editor_arc.lock().unwrap();
-                        editor.build_status = BuildStatus::Failed(format!("Compiler failed: {}", String::from_utf8_lossy(&output.stderr)));
+                        editor.build_status = BuildStatus::Failed(format!("Compiler failed: {}", summary));
+                        editor.set_compile_errors(compile_errors);
                     }
                 }
                 Err(e) => {
@@ -433,11 +818,107 @@ pub fn build_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMes
     }
 }
 
+// Runs the transpiled project with cargo and streams its output into the build-output pane as it arrives.
+// The TUI owns the terminal, so the program gets no stdin and interactive programs need to be run from a shell.
+fn run_transpiled_program(editor_arc: &Arc<Mutex<Editor>>, transpilation_dir: &Path, transpiler: &Transpiler) {
+    {
+        let mut editor = editor_arc.lock().unwrap();
+        editor.build_status = BuildStatus::Running;
+        editor.dialog_mode = DialogMode::BuildOutput;
+    }
+
+    let child = Command::new("cargo")
+        .arg("run")
+        .arg("--release")
+        .arg("--quiet")
+        .arg("--message-format=short")
+        .current_dir(transpilation_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Failed to execute cargo: {}", e);
+            let mut editor = editor_arc.lock().unwrap();
+            editor.build_status = BuildStatus::Failed(format!("Failed to execute cargo: {}", e));
+            return;
+        }
+    };
+
+    // stderr is drained on its own thread so a chatty program can't block on a full pipe
+    let stderr = child.stderr.take().expect("child stderr is piped");
+    let stderr_editor_arc = Arc::clone(editor_arc);
+    let stderr_thread = thread::spawn(move || {
+        let mut stderr_text = String::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            stderr_text.push_str(&line);
+            stderr_text.push('\n');
+            stderr_editor_arc.lock().unwrap().build_output.push(line);
+        }
+        stderr_text
+    });
+
+    let stdout = child.stdout.take().expect("child stdout is piped");
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        editor_arc.lock().unwrap().build_output.push(line);
+    }
+
+    let stderr_text = stderr_thread.join().unwrap_or_default();
+    let status = child.wait();
+
+    let mut editor = editor_arc.lock().unwrap();
+    match status {
+        Ok(status) if status.success() => {
+            editor.build_status = BuildStatus::Complete;
+            editor.build_time = editor.build_started.map(|started| started.elapsed());
+        }
+        Ok(status) => {
+            let compile_errors = summarize_compiler_errors(&stderr_text, transpiler);
+            editor.build_status = match compile_errors.first() {
+                Some(error) => BuildStatus::Failed(format!("Compiler failed: {}", error.message)),
+                None => BuildStatus::Failed(format!("Program exited with {}", status)),
+            };
+            editor.set_compile_errors(compile_errors);
+        }
+        Err(e) => editor.build_status = BuildStatus::Failed(format!("Failed to wait for cargo: {}", e)),
+    }
+}
+
+// Rewriting an unchanged file would bump its mtime and make cargo recompile it, so identical contents are left alone
+fn write_if_changed(path: &Path, contents: &str) -> io::Result<()> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+    fs::write(path, contents)
+}
+
+// Picks the errors out of cargo's --message-format=short output, e.g.
+// "src/main.rs:12:5: error[E0308]: mismatched types". Each error points at the Nail statement the
+// generated Rust line came from, and the Rust location is kept in the message.
+fn summarize_compiler_errors(stderr: &str, transpiler: &Transpiler) -> Vec<CodeError> {
+    let error_line = Regex::new(r"^(\S+):(\d+):(\d+): error(\[\w+\])?: (.*)$").unwrap();
+    stderr
+        .lines()
+        .filter_map(|line| error_line.captures(line.trim()))
+        .map(|captures| CodeError {
+            message: format!("{} (generated Rust {}:{})", &captures[5], &captures[2], &captures[3]),
+            code_span: captures[2].parse().ok().and_then(|rust_line| transpiler.nail_span(rust_line)).unwrap_or_default(),
+        })
+        .collect()
+}
+
 pub fn lex_and_parse_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<EditorMessage>) {
+    // A burst of keystrokes is analysed once, after the content has stopped changing for the editor's debounce
+    let mut last_content = String::new();
+    let mut changed_at: Option<Instant> = None;
+
     loop {
-        // Check for shutdown message
-        match rx.try_recv() {
-            Ok(EditorMessage::Shutdown) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+        // Waiting on the channel is the poll interval, so a shutdown is still picked up straight away
+        match rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(EditorMessage::Shutdown) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                 log::info!("Shutting down syntax error thread");
                 break;
             }
@@ -445,64 +926,154 @@ pub fn lex_and_parse_thread_logic(editor_arc: Arc<Mutex<Editor>>, rx: Receiver<E
         }
 
         // Lock the editor to access its content
-        let content = {
+        let (content, debounce) = {
             let editor = lock(&editor_arc);
-            editor.content.join("\n")
+            (editor.content.join("\n"), editor.debounce)
         };
 
+        if content != last_content {
+            last_content = content;
+            changed_at = Some(Instant::now());
+            continue;
+        }
+
+        match changed_at {
+            Some(changed) if changed.elapsed() >= debounce => changed_at = None,
+            _ => continue,
+        }
+
         // Run the lexer on the content
         let tokens = lexer::lexer(&content);
 
-        {
-            let mut editor = lock(&editor_arc);
-            editor.tokens = tokens.clone();
+        // Edits that only move tokens around, like new blank lines or indentation, don't need a re-check.
+        // The errors already found are moved along with the tokens they point at instead.
+        let tokens_changed = {
+            let mut guard = lock(&editor_arc);
+            let editor = &mut *guard;
+            let previous_tokens = std::mem::replace(&mut editor.tokens, tokens.clone());
+            let tokens_changed = without_spans(&previous_tokens) != without_spans(&tokens);
+            if !tokens_changed {
+                for error in editor.code_errors.iter_mut().chain(editor.compile_errors.iter_mut()) {
+                    error.code_span = shift_span(&error.code_span, &previous_tokens, &tokens);
+                }
+            }
+            tokens_changed
+        };
+
+        if !tokens_changed {
+            continue;
         }
 
         // Check for error tokens
-        let mut lexing_error = None;
-        for token in tokens.clone() {
-            if let lexer::TokenType::LexerError(message) = token.token_type {
-                lexing_error = Some(CodeError { message: format!("^ {}", message), code_span: token.code_span });
-                break;
-            }
-        }
+        let lexing_errors = lexing_errors(&tokens);
 
         {
             let mut editor = lock(&editor_arc);
-            editor.code_error = lexing_error.clone();
+            editor.set_code_errors(lexing_errors.clone());
         }
 
-        if lexing_error.is_some() {
-            log::info!("Lexer error detected: {:?}", lexing_error);
-            // Sleep for a while to avoid excessive CPU usage, no need to parse if there are lexer errors
-            thread::sleep(Duration::from_millis(250));
+        if !lexing_errors.is_empty() {
+            log::info!("Lexer errors detected: {:?}", lexing_errors);
+            // No need to parse if there are lexer errors
+            lock(&editor_arc).transpile_preview_stale = true;
             continue;
         }
 
         // if the above is successful, get the parser errors and do the same thing
 
-        let mut ast = match parse(tokens) {
-            Ok(ast) => ast,
+        let (mut ast, parsed) = match parse(tokens) {
+            Ok(ast) => (ast, true),
             Err(e) => {
                 let mut editor = lock(&editor_arc);
-                editor.code_error = Some(CodeError { message: format!("^ {}", e.message), code_span: e.code_span });
-                ASTNode::default()
+                editor.set_code_errors(vec![e]);
+                (ASTNode::default(), false)
             }
         };
 
-        let _ = match checker(&mut ast) {
-            Ok(_) => {}
+        let checked = match checker(&mut ast) {
+            Ok(_) => true,
             Err(errors) => {
                 let mut editor = lock(&editor_arc);
-                editor.code_error = Some(CodeError { message: format!("^ {}", errors[0].message), code_span: errors[0].code_span.clone() });
+                editor.set_code_errors(errors);
+                false
             }
         };
 
-        // Sleep to avoid excessive CPU usage
-        thread::sleep(Duration::from_millis(250));
+        // Code that checks cleanly is transpiled for the preview pane, otherwise the last good output is kept
+        let rust_code = if parsed && checked { transpile_for_preview(&ast) } else { None };
+        let mut editor = lock(&editor_arc);
+        match rust_code {
+            Some(rust_code) => {
+                editor.transpile_preview = rust_code.lines().map(String::from).collect();
+                editor.transpile_preview_stale = false;
+            }
+            None => editor.transpile_preview_stale = true,
+        }
     }
 }
 
+// The lexer reports problems as error tokens in the stream rather than failing
+// The tokens with every span cleared, nested ones included, so two lexes can be compared by content alone
+fn without_spans(tokens: &[lexer::Token]) -> Vec<lexer::Token> {
+    let mut tokens = tokens.to_vec();
+    clear_spans(&mut tokens);
+    tokens
+}
+
+fn clear_spans(tokens: &mut [lexer::Token]) {
+    for token in tokens {
+        token.code_span = lexer::CodeSpan::default();
+        match &mut token.token_type {
+            lexer::TokenType::Array(nested)
+            | lexer::TokenType::LambdaSignature(nested)
+            | lexer::TokenType::FunctionSignature(nested)
+            | lexer::TokenType::InterpolatedString(nested)
+            | lexer::TokenType::Interpolation(nested)
+            | lexer::TokenType::RustEscape(nested)
+            | lexer::TokenType::RustNailInsert(nested) => clear_spans(nested),
+            lexer::TokenType::StructDeclaration(data) => data.fields.iter_mut().for_each(|field| {
+                field.type_span = lexer::CodeSpan::default();
+                field.code_span = lexer::CodeSpan::default();
+            }),
+            lexer::TokenType::StructInstantiation(data) => data.fields.iter_mut().for_each(|field| clear_spans(std::slice::from_mut(&mut field.value))),
+            lexer::TokenType::EnumDeclaration(data) => clear_spans(&mut data.variants),
+            _ => {}
+        }
+    }
+}
+
+// Moves a span by as much as the token it starts in, the last one starting at or before it, moved between two lexes
+// of the same tokens. Columns only move when the span is on that token's first line.
+fn shift_span(span: &lexer::CodeSpan, old_tokens: &[lexer::Token], new_tokens: &[lexer::Token]) -> lexer::CodeSpan {
+    let Some((old, new)) = old_tokens.iter().zip(new_tokens).take_while(|(old, _)| (old.code_span.start_line, old.code_span.start_column) <= (span.start_line, span.start_column)).last() else {
+        return span.clone();
+    };
+    let line_delta = new.code_span.start_line as isize - old.code_span.start_line as isize;
+    let column_delta = new.code_span.start_column as isize - old.code_span.start_column as isize;
+    let shift_column = |line: usize, column: usize| if line == old.code_span.start_line { column.saturating_add_signed(column_delta) } else { column };
+    lexer::CodeSpan {
+        start_line: span.start_line.saturating_add_signed(line_delta),
+        start_column: shift_column(span.start_line, span.start_column),
+        end_line: span.end_line.saturating_add_signed(line_delta),
+        end_column: shift_column(span.end_line, span.end_column),
+    }
+}
+
+pub fn lexing_errors(tokens: &[lexer::Token]) -> Vec<CodeError> {
+    tokens
+        .iter()
+        .filter_map(|token| match &token.token_type {
+            lexer::TokenType::LexerError(message) => Some(CodeError { message: message.clone(), code_span: token.code_span.clone() }),
+            _ => None,
+        })
+        .collect()
+}
+
+// The transpiler still panics on a few unsupported nodes, which mustn't take the analysis thread down with it
+fn transpile_for_preview(ast: &ASTNode) -> Option<String> {
+    panic::catch_unwind(panic::AssertUnwindSafe(|| Transpiler::new().transpile(ast))).ok()?.ok()
+}
+
 pub fn create_transpilation_cargo_toml() -> String {
     r#"
     [package]
@@ -525,8 +1096,9 @@ static WELCOME_MESSAGE: &str = r#"c welcome:s = `Welcome to NAIL - alpha version
 c example_text:s = `Here are some quick tips to get you started:
 1. Type your code in this editor
 2. Use F6 to toggle between light and dark themes
-3. Press F7 to build and run your code
-4. Use Ctrl + C or Esc to exit the editor
+3. Press F7 to build and run your code, or F5 to run it and watch the output
+4. Press Ctrl + P to search all commands and their keys
+5. Use Ctrl + C or Esc to exit the editor
 
 Let's start with some cool examples:`;
 "#;
@@ -539,3 +1111,81 @@ Let's start with some cool examples:`;
 pub fn create_welcome_message() -> Vec<String> {
     WELCOME_MESSAGE.lines().map(String::from).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::KeyChord;
+
+    #[test]
+    fn test_summarize_compiler_errors() {
+        let stderr = "   Compiling nail_transpilation v0.1.0 (/tmp/nail_transpilation)
+src/main.rs:4:20: error[E0425]: cannot find value `missing` in this scope: not found in this scope
+src/main.rs:3:9: warning: unused variable: `unused`: help: if this is intentional, prefix it with an underscore: `_unused`
+src/main.rs:2:22: error[E0308]: mismatched types: expected `i64`, found `&str`
+error: could not compile `nail_transpilation` (bin \"nail_transpilation\") due to 2 previous errors; 1 warning emitted
+";
+        let errors = summarize_compiler_errors(stderr, &Transpiler::new());
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(messages, vec!["cannot find value `missing` in this scope: not found in this scope (generated Rust 4:20)", "mismatched types: expected `i64`, found `&str` (generated Rust 2:22)",]);
+        assert!(errors.iter().all(|error| error.code_span.start_line == 0));
+    }
+
+    #[test]
+    fn test_remapped_toggle_chords_close_their_dialogs() {
+        let (tx_build, _rx_build) = std::sync::mpsc::channel();
+        let mut editor = Editor::new();
+        for (command, chord) in editor.keybindings.iter_mut() {
+            match command {
+                EditorCommand::ToggleDiagnostics => *chord = KeyChord::new(KeyCode::F(2), KeyModifiers::NONE),
+                EditorCommand::ToggleBuildOutput => *chord = KeyChord::new(KeyCode::Char('b'), KeyModifiers::ALT),
+                _ => {}
+            }
+        }
+
+        editor.toggle_diagnostics();
+        handle_dialog_key(&mut editor, KeyCode::F(9), KeyModifiers::NONE, &tx_build);
+        assert_eq!(editor.dialog_mode, DialogMode::Diagnostics);
+        handle_dialog_key(&mut editor, KeyCode::F(2), KeyModifiers::NONE, &tx_build);
+        assert_eq!(editor.dialog_mode, DialogMode::None);
+
+        editor.toggle_build_output();
+        handle_dialog_key(&mut editor, KeyCode::Char('b'), KeyModifiers::ALT, &tx_build);
+        assert_eq!(editor.dialog_mode, DialogMode::None);
+
+        editor.open_command_palette();
+        handle_dialog_key(&mut editor, KeyCode::Char('p'), KeyModifiers::CONTROL, &tx_build);
+        assert_eq!(editor.dialog_mode, DialogMode::None);
+        assert_eq!(editor.command_palette_query, "");
+    }
+
+    #[test]
+    fn test_moved_tokens_carry_their_errors_along() {
+        let old_tokens = lexer::lexer("c first:i = 1;\nc second:s = first;");
+        let new_tokens = lexer::lexer("c first:i = 1;\n\n  c second:s = first;");
+        assert_eq!(without_spans(&old_tokens), without_spans(&new_tokens));
+        assert_ne!(without_spans(&old_tokens), without_spans(&lexer::lexer("c first:i = 1;\nc second:s = other;")));
+
+        let first = lexer::CodeSpan { start_line: 2, start_column: 14, end_line: 2, end_column: 19 };
+        assert_eq!(shift_span(&first, &old_tokens, &new_tokens), lexer::CodeSpan { start_line: 3, start_column: 16, end_line: 3, end_column: 21 });
+
+        // Spans before every token and span-less errors stay where they are
+        let unchanged = lexer::CodeSpan { start_line: 1, start_column: 1, end_line: 1, end_column: 2 };
+        assert_eq!(shift_span(&unchanged, &old_tokens, &new_tokens), unchanged);
+        assert_eq!(shift_span(&lexer::CodeSpan::default(), &old_tokens, &new_tokens), lexer::CodeSpan::default());
+    }
+
+    #[test]
+    fn test_compiler_errors_point_at_the_nail_statement() {
+        let mut transpiler = Transpiler::new();
+        let rust_code = transpiler.transpile(&parse(lexer::lexer("c first:i = 1;\nfn double(num:i):i {\n    r num * 2;\n}\ndouble(first);")).unwrap()).unwrap();
+        assert_eq!(rust_code.lines().nth(7).unwrap().trim(), "return num * 2    }");
+
+        let stderr = "src/main.rs:8:16: error[E0308]: mismatched types: expected `i64`, found `f64`
+src/main.rs:10:5: error[E0425]: cannot find function `double` in this scope: not found in this scope
+src/main.rs:1:5: error[E0432]: unresolved import `tokio`: use of unresolved module or unlinked crate `tokio`
+";
+        let lines: Vec<usize> = summarize_compiler_errors(stderr, &transpiler).iter().map(|error| error.code_span.start_line).collect();
+        assert_eq!(lines, vec![3, 5, 0]);
+    }
+}